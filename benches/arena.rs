@@ -0,0 +1,82 @@
+mod common;
+
+use std::{hint::black_box, time::Duration};
+
+use common::{fastest, random_keys};
+use nasp_homework::rbtree::{ArenaRbTree, RbTree};
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+fn report(operation: &str, len: usize, pointer: Duration, arena: Duration) {
+    println!(
+        "{:<8} {:>8} {:>12.3?} {:>12.3?} {:>8.2}x",
+        operation,
+        len,
+        pointer,
+        arena,
+        pointer.as_secs_f64() / arena.as_secs_f64(),
+    );
+}
+
+fn main() {
+    println!(
+        "{:<8} {:>8} {:>12} {:>12} {:>9}",
+        "op", "n", "pointer", "arena", "speedup"
+    );
+
+    for len in SIZES {
+        let keys = random_keys(len);
+        let pointer_tree: RbTree<u64> = keys.iter().copied().collect();
+        let arena_tree: ArenaRbTree<u64> = keys.iter().copied().collect();
+
+        let pointer = fastest(RbTree::new, |mut tree| {
+            for &key in &keys {
+                tree.insert(key);
+            }
+            tree
+        });
+        let arena = fastest(ArenaRbTree::new, |mut tree| {
+            for &key in &keys {
+                tree.insert(key);
+            }
+            tree
+        });
+        report("insert", len, pointer, arena);
+
+        let pointer = fastest(
+            || (),
+            |_| {
+                for key in &keys {
+                    black_box(pointer_tree.contains(key));
+                }
+            },
+        );
+        let arena = fastest(
+            || (),
+            |_| {
+                for key in &keys {
+                    black_box(arena_tree.contains(key));
+                }
+            },
+        );
+        report("lookup", len, pointer, arena);
+
+        let pointer = fastest(
+            || pointer_tree.clone(),
+            |mut tree| {
+                for key in &keys {
+                    black_box(tree.delete(key));
+                }
+            },
+        );
+        let arena = fastest(
+            || arena_tree.clone(),
+            |mut tree| {
+                for key in &keys {
+                    black_box(tree.delete(key));
+                }
+            },
+        );
+        report("delete", len, pointer, arena);
+    }
+}
@@ -0,0 +1,283 @@
+mod common;
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, BinaryHeap},
+    hint::black_box,
+    time::Duration,
+};
+
+use common::{fastest, random_keys};
+use nasp_homework::{fibheap::FibHeap, rbtree::RbTree};
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+#[derive(Clone, Copy)]
+enum Input {
+    Random,
+    Sorted,
+    Adversarial,
+}
+
+impl Input {
+    fn name(self) -> &'static str {
+        match self {
+            Input::Random => "random",
+            Input::Sorted => "sorted",
+            Input::Adversarial => "adversarial",
+        }
+    }
+
+    // Adversarial input alternates between the largest and smallest remaining
+    // key, so every insert lands on the far edge of the tree and every push
+    // becomes the new minimum half the time.
+    fn keys(self, len: usize) -> Vec<u64> {
+        match self {
+            Input::Random => random_keys(len),
+            Input::Sorted => (0..len as u64).collect(),
+            Input::Adversarial => {
+                let (mut low, mut high) = (0, len as u64);
+                (0..len)
+                    .map(|i| {
+                        if i % 2 == 0 {
+                            high -= 1;
+                            high
+                        } else {
+                            low += 1;
+                            low - 1
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn report(
+    structure: &str,
+    operation: &str,
+    input: Input,
+    len: usize,
+    ours: Duration,
+    std: Duration,
+) {
+    println!(
+        "{:<16} {:<14} {:<12} {:>8} {:>12.3?} {:>12.3?} {:>8.2}x",
+        structure,
+        operation,
+        input.name(),
+        len,
+        ours,
+        std,
+        std.as_secs_f64() / ours.as_secs_f64(),
+    );
+}
+
+fn halves(keys: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    let left = keys.iter().step_by(2).copied().collect();
+    let right = keys.iter().skip(1).step_by(2).copied().collect();
+    (left, right)
+}
+
+fn bench_sets(input: Input, len: usize) {
+    let keys = input.keys(len);
+    let rb_tree: RbTree<u64> = keys.iter().copied().collect();
+    let b_tree: BTreeSet<u64> = keys.iter().copied().collect();
+
+    let ours = fastest(RbTree::new, |mut tree| {
+        for &key in &keys {
+            tree.insert(key);
+        }
+        tree
+    });
+    let std = fastest(BTreeSet::new, |mut tree| {
+        for &key in &keys {
+            tree.insert(key);
+        }
+        tree
+    });
+    report("RbTree/BTreeSet", "insert", input, len, ours, std);
+
+    let ours = fastest(
+        || rb_tree.clone(),
+        |mut tree| {
+            for key in &keys {
+                black_box(tree.delete(key));
+            }
+        },
+    );
+    let std = fastest(
+        || b_tree.clone(),
+        |mut tree| {
+            for key in &keys {
+                black_box(tree.remove(key));
+            }
+        },
+    );
+    report("RbTree/BTreeSet", "delete", input, len, ours, std);
+
+    let ours = fastest(
+        || (),
+        |_| {
+            for key in &keys {
+                black_box(rb_tree.contains(key));
+            }
+        },
+    );
+    let std = fastest(
+        || (),
+        |_| {
+            for key in &keys {
+                black_box(b_tree.contains(key));
+            }
+        },
+    );
+    report("RbTree/BTreeSet", "lookup", input, len, ours, std);
+
+    let ours = fastest(|| (), |_| rb_tree.iter().fold(0, |sum, x| sum ^ x));
+    let std = fastest(|| (), |_| b_tree.iter().fold(0, |sum, x| sum ^ x));
+    report("RbTree/BTreeSet", "iteration", input, len, ours, std);
+
+    let (left, right) = halves(&keys);
+    let ours = fastest(
+        || {
+            let left: RbTree<u64> = left.iter().copied().collect();
+            let right: RbTree<u64> = right.iter().copied().collect();
+            (left, right)
+        },
+        |(mut left, right)| {
+            left.union_with(right);
+            left
+        },
+    );
+    let std = fastest(
+        || {
+            let left: BTreeSet<u64> = left.iter().copied().collect();
+            let right: BTreeSet<u64> = right.iter().copied().collect();
+            (left, right)
+        },
+        |(mut left, mut right)| {
+            left.append(&mut right);
+            left
+        },
+    );
+    report("RbTree/BTreeSet", "union", input, len, ours, std);
+}
+
+fn bench_heaps(input: Input, len: usize) {
+    let keys = input.keys(len);
+
+    let ours = fastest(FibHeap::new, |mut heap| {
+        for &key in &keys {
+            heap.push(key);
+        }
+        heap
+    });
+    let std = fastest(BinaryHeap::new, |mut heap| {
+        for &key in &keys {
+            heap.push(Reverse(key));
+        }
+        heap
+    });
+    report("FibHeap/BinHeap", "push", input, len, ours, std);
+
+    let ours = fastest(
+        || keys.iter().copied().collect::<FibHeap<u64>>(),
+        |mut heap| {
+            while let Some(key) = heap.pop() {
+                black_box(key);
+            }
+        },
+    );
+    let std = fastest(
+        || keys.iter().copied().map(Reverse).collect::<BinaryHeap<_>>(),
+        |mut heap| {
+            while let Some(key) = heap.pop() {
+                black_box(key);
+            }
+        },
+    );
+    report("FibHeap/BinHeap", "pop", input, len, ours, std);
+
+    // FibHeap lowers each priority in place through the handle returned by
+    // push_with_handle. BinaryHeap has no handles, so it does what a
+    // Dijkstra-style caller would: push the lowered priority and skip the
+    // stale entry when popped.
+    let entries: Vec<(u64, u32)> = keys.iter().zip(0..).map(|(&key, id)| (key, id)).collect();
+    let lowered: Vec<u64> = keys.iter().map(|key| key / 2).collect();
+
+    let ours = fastest(
+        || {
+            let mut heap = FibHeap::new();
+            let handles: Vec<_> = entries
+                .iter()
+                .map(|&entry| heap.push_with_handle(entry))
+                .collect();
+            (heap, handles)
+        },
+        |(mut heap, handles)| {
+            for (id, (&priority, &handle)) in lowered.iter().zip(&handles).enumerate() {
+                heap.decrease_key(handle, (priority, id as u32)).unwrap();
+            }
+            while let Some((_, id)) = heap.pop() {
+                black_box(id);
+            }
+        },
+    );
+    let std = fastest(
+        || {
+            entries
+                .iter()
+                .copied()
+                .map(Reverse)
+                .collect::<BinaryHeap<_>>()
+        },
+        |mut heap| {
+            for (id, &priority) in lowered.iter().enumerate() {
+                heap.push(Reverse((priority, id as u32)));
+            }
+            while let Some(Reverse((priority, id))) = heap.pop() {
+                if priority == lowered[id as usize] {
+                    black_box(id);
+                }
+            }
+        },
+    );
+    report("FibHeap/BinHeap", "decrease_key", input, len, ours, std);
+
+    let (left, right) = halves(&keys);
+    let ours = fastest(
+        || {
+            let left: FibHeap<u64> = left.iter().copied().collect();
+            let right: FibHeap<u64> = right.iter().copied().collect();
+            (left, right)
+        },
+        |(left, right)| FibHeap::union(left, right),
+    );
+    let std = fastest(
+        || {
+            let left: BinaryHeap<_> = left.iter().copied().map(Reverse).collect();
+            let right: BinaryHeap<_> = right.iter().copied().map(Reverse).collect();
+            (left, right)
+        },
+        |(mut left, mut right)| {
+            left.append(&mut right);
+            left
+        },
+    );
+    report("FibHeap/BinHeap", "union", input, len, ours, std);
+}
+
+fn main() {
+    println!(
+        "{:<16} {:<14} {:<12} {:>8} {:>12} {:>12} {:>9}",
+        "structure", "op", "input", "n", "ours", "std", "speedup"
+    );
+
+    for len in SIZES {
+        for input in [Input::Random, Input::Sorted, Input::Adversarial] {
+            bench_sets(input, len);
+            bench_heaps(input, len);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+const RUNS: usize = 5;
+
+pub fn random_keys(len: usize) -> Vec<u64> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        })
+        .collect()
+}
+
+// The routine's output is dropped after the clock stops, so freeing the
+// structure under test is not counted.
+pub fn fastest<T, R>(mut setup: impl FnMut() -> T, mut routine: impl FnMut(T) -> R) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let input = setup();
+            let start = Instant::now();
+            let output = black_box(routine(input));
+            let elapsed = start.elapsed();
+            drop(output);
+            elapsed
+        })
+        .min()
+        .unwrap()
+}
@@ -1,21 +1,52 @@
-use num::traits::bounds::Bounded;
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+#[cfg(feature = "serde")]
+mod serde;
+
+type Index = u32;
 
 pub struct FibHeap<T> {
-    roots: Vec<Tree<T>>,
+    slots: Vec<Slot<T>>,
+    free: Vec<Index>,
+    min: Option<Index>,
     len: usize,
 }
 
+struct Slot<T> {
+    tree: Option<Tree<T>>,
+    generation: u32,
+}
+
+// Siblings form a circular list through `left` and `right`, and the roots
+// are the list that `min` belongs to, so any tree unlinks in O(1).
 struct Tree<T> {
     node: T,
-    children: Vec<Tree<T>>,
-    parent: Option<*mut Tree<T>>,
+    parent: Option<Index>,
+    child: Option<Index>,
+    left: Index,
+    right: Index,
+    degree: u32,
     mark: bool,
 }
 
-impl<T: Ord + Bounded + Clone> FibHeap<T> {
+// A handle names the element pushed into one particular slot. Slots are
+// reused after a pop, so the generation tells a stale handle apart from the
+// element that took its place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    index: Index,
+    generation: u32,
+}
+
+impl<T: Ord> FibHeap<T> {
     pub fn new() -> Self {
         Self {
-            roots: Default::default(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            min: None,
             len: 0,
         }
     }
@@ -24,195 +55,323 @@ impl<T: Ord + Bounded + Clone> FibHeap<T> {
         self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn peek(&self) -> Option<&T> {
-        self.roots.last().map(Tree::root)
+        self.min.map(|index| &self.tree(index).node)
     }
 
-    pub fn push(&mut self, item: T) {
-        let new_min = self.peek().map(|o| &item <= o).unwrap_or(true);
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.index_of(handle).map(|index| &self.tree(index).node)
+    }
 
-        self.roots.push(Tree::new(item));
+    pub fn push(&mut self, item: T) {
+        self.push_with_handle(item);
+    }
 
-        if !new_min {
-            let i = self.roots.len() - 1;
-            self.roots.swap(i - 1, i);
-        }
+    pub fn push_with_handle(&mut self, item: T) -> Handle {
+        let handle = self.allocate(item);
+        self.add_root(handle.index);
 
         self.len += 1;
+
+        handle
     }
 
     pub fn pop(&mut self) -> Option<T> {
-        let Tree {
-            node, mut children, ..
-        } = match self.roots.pop() {
-            Some(x) => x,
-            None => return None,
-        };
+        let min = self.min?;
 
-        self.len -= 1;
+        Some(self.remove_root(min))
+    }
 
-        for child in &mut children {
-            child.parent = None;
-        }
+    pub fn decrease_key(&mut self, handle: Handle, new_value: T) -> Result<(), DecreaseKeyError> {
+        let index = self.index_of(handle).ok_or(DecreaseKeyError::StaleHandle)?;
 
-        self.roots.extend(children);
+        if new_value > self.tree(index).node {
+            return Err(DecreaseKeyError::KeyIncreased);
+        }
 
-        Self::rebalance(&mut self.roots, self.len);
-        Self::order_min(&mut self.roots);
+        self.tree_mut(index).node = new_value;
 
-        Some(node)
-    }
+        if let Some(parent) = self.tree(index).parent {
+            if self.tree(index).node < self.tree(parent).node {
+                self.cut(index);
+                self.cascading_cut(parent);
+            }
+        }
 
-    fn rebalance(roots: &mut Vec<Tree<T>>, nodes: usize) {
-        if roots.is_empty() {
-            return;
+        if self.tree(index).parent.is_none() && self.tree(index).node < *self.peek().unwrap() {
+            self.min = Some(index);
         }
 
-        let cap = nodes.ilog2() + 1;
+        Ok(())
+    }
 
-        let mut buf: Vec<Option<Tree<T>>> =
-            std::iter::repeat_with(|| None).take(cap as usize).collect();
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let index = self.index_of(handle)?;
 
-        while let Some(mut tree) = roots.pop() {
-            loop {
-                let degree = tree.degree();
+        if let Some(parent) = self.tree(index).parent {
+            self.cut(index);
+            self.cascading_cut(parent);
+        }
 
-                debug_assert!(
-                    degree < cap as usize,
-                    "Degree is greater than log2(len) + 1"
-                );
+        Some(self.remove_root(index))
+    }
 
-                tree = match buf[degree].take() {
-                    None => {
-                        buf[degree] = Some(tree);
-                        break;
-                    }
-                    Some(tree_b) if tree.root() <= tree_b.root() => {
-                        tree.children.push(tree_b);
-                        tree
-                    }
-                    Some(mut tree_b) => {
-                        tree_b.children.push(tree);
-                        tree_b
-                    }
+    // Handles into `second` do not carry over to the result.
+    pub fn union(mut first: Self, second: Self) -> Self {
+        let offset = Index::try_from(first.slots.len()).expect("heap index overflow");
+        let shift = |index: Index| index + offset;
+
+        first.slots.extend(second.slots.into_iter().map(|mut slot| {
+            if let Some(tree) = &mut slot.tree {
+                tree.parent = tree.parent.map(shift);
+                tree.child = tree.child.map(shift);
+                tree.left += offset;
+                tree.right += offset;
+            }
+            slot
+        }));
+        first.free.extend(second.free.into_iter().map(shift));
+
+        match (first.min, second.min.map(shift)) {
+            (_, None) => (),
+            (None, min) => first.min = min,
+            (Some(min), Some(other)) => {
+                first.splice(min, other);
+
+                if first.tree(other).node < first.tree(min).node {
+                    first.min = Some(other);
                 }
             }
         }
 
-        roots.extend(buf.into_iter().filter_map(|x| x));
-    }
+        first.len += second.len;
 
-    fn order_min(roots: &mut Vec<Tree<T>>) {
-        let min_index = roots
-            .iter()
-            .enumerate()
-            .min_by_key(|(_, t)| t.root())
-            .map(|(idx, _)| idx);
+        first
+    }
 
-        if let Some(idx) = min_index {
-            let lastidx = roots.len() - 1;
-            roots.swap(idx, lastidx);
-        }
+    fn tree(&self, index: Index) -> &Tree<T> {
+        self.slots[index as usize].tree.as_ref().unwrap()
     }
 
-    pub fn union(mut first: Self, mut second: Self) -> Self {
-        let mut new = Self::new();
+    fn tree_mut(&mut self, index: Index) -> &mut Tree<T> {
+        self.slots[index as usize].tree.as_mut().unwrap()
+    }
 
-        let i = first.roots.len();
-        let j = second.roots.len();
+    fn index_of(&self, handle: Handle) -> Option<Index> {
+        let slot = self.slots.get(handle.index as usize)?;
 
-        let swap_min = i != 0 && j != 0 && first.peek() < second.peek();
+        (slot.generation == handle.generation && slot.tree.is_some()).then_some(handle.index)
+    }
 
-        new.roots.append(&mut first.roots);
-        new.roots.append(&mut second.roots);
+    fn allocate(&mut self, item: T) -> Handle {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    tree: None,
+                    generation: 0,
+                });
+                Index::try_from(self.slots.len() - 1).expect("heap index overflow")
+            }
+        };
 
-        new.len = first.len + second.len;
-        first.len = 0;
-        second.len = 0;
+        let slot = &mut self.slots[index as usize];
+        slot.tree = Some(Tree::new(item, index));
 
-        if swap_min {
-            new.roots.swap(i - 1, i + j - 1);
+        Handle {
+            index,
+            generation: slot.generation,
         }
+    }
+
+    fn release(&mut self, index: Index) -> Tree<T> {
+        let slot = &mut self.slots[index as usize];
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(index);
 
-        new
+        slot.tree.take().unwrap()
     }
 
-    fn decrease_key(&mut self, tree_ptr: *mut Tree<T>, new_value: T) {
-        unsafe {
-            let tree = &mut *tree_ptr;
+    // Joins the circular lists that `a` and `b` belong to.
+    fn splice(&mut self, a: Index, b: Index) {
+        let a_right = self.tree(a).right;
+        let b_left = self.tree(b).left;
 
-            debug_assert!(
-                new_value <= tree.node,
-                "New value must be less than the current value"
-            );
+        self.tree_mut(a).right = b;
+        self.tree_mut(b).left = a;
+        self.tree_mut(a_right).left = b_left;
+        self.tree_mut(b_left).right = a_right;
+    }
 
-            let old_max = self.peek().cloned();
-            let old_roots_len = self.roots.len();
+    fn unlink(&mut self, index: Index) {
+        let Tree { left, right, .. } = *self.tree(index);
 
-            tree.node = new_value.clone();
+        self.tree_mut(left).right = right;
+        self.tree_mut(right).left = left;
+
+        let tree = self.tree_mut(index);
+        tree.left = index;
+        tree.right = index;
+    }
 
-            if let Some(parent_ptr) = tree.parent {
-                let parent = &mut *parent_ptr;
+    // `index` must not be linked to any siblings.
+    fn add_root(&mut self, index: Index) {
+        self.tree_mut(index).parent = None;
 
-                if tree.node < parent.node {
-                    self.cut(tree_ptr);
-                    self.cascading_cut(parent_ptr);
+        match self.min {
+            None => self.min = Some(index),
+            Some(min) => {
+                self.splice(min, index);
+
+                if self.tree(index).node < self.tree(min).node {
+                    self.min = Some(index);
                 }
             }
+        }
+    }
 
-            if let Some(omax) = old_max {
-                if old_roots_len != self.roots.len() && new_value < omax {
-                    self.roots.swap(old_roots_len - 1, old_roots_len)
+    fn remove_root(&mut self, index: Index) -> T {
+        if let Some(child) = self.tree(index).child {
+            let mut current = child;
+            loop {
+                let tree = self.tree_mut(current);
+                tree.parent = None;
+                current = tree.right;
+
+                if current == child {
+                    break;
                 }
             }
+
+            self.splice(index, child);
         }
-    }
 
-    fn delete(&mut self, tree_ptr: *mut Tree<T>) {
-        self.decrease_key(tree_ptr, T::min_value());
-        self.pop();
+        let next = self.tree(index).right;
+        self.unlink(index);
+
+        let Tree { node, .. } = self.release(index);
+
+        self.len -= 1;
+        self.min = None;
+
+        if next != index {
+            self.rebalance(next);
+        }
+
+        node
     }
 
-    fn cut(&mut self, tree_ptr: *mut Tree<T>) {
-        unsafe {
-            let tree = &mut *tree_ptr;
+    fn rebalance(&mut self, start: Index) {
+        let cap = self.len.ilog2() + 1;
+
+        let mut buf: Vec<Option<Index>> = vec![None; cap as usize];
 
-            if let Some(parent_ptr) = tree.parent {
-                let parent = &mut *parent_ptr;
+        // Linking only resets the links of roots already visited, so the walk
+        // can follow `right` through the roots that are still ahead of it.
+        let mut next = start;
+        loop {
+            let mut tree = next;
+            next = self.tree(tree).right;
 
-                let index = parent
-                    .children
-                    .iter()
-                    .position(|child| std::ptr::eq(child, tree));
+            loop {
+                let degree = self.tree(tree).degree as usize;
 
-                if let Some(idx) = index {
-                    parent.children.swap_remove(idx);
+                // Cuts loosen the binomial shape, so degrees can exceed log2(len).
+                if degree >= buf.len() {
+                    buf.resize(degree + 1, None);
                 }
 
-                tree.parent = None;
-                tree.mark = false;
-                self.roots.push(tree.take());
+                tree = match buf[degree].take() {
+                    None => {
+                        buf[degree] = Some(tree);
+                        break;
+                    }
+                    Some(tree_b) if self.tree(tree).root() <= self.tree(tree_b).root() => {
+                        self.link(tree_b, tree);
+                        tree
+                    }
+                    Some(tree_b) => {
+                        self.link(tree, tree_b);
+                        tree_b
+                    }
+                }
+            }
+
+            if next == start {
+                break;
             }
         }
+
+        // The old root list is rebuilt from scratch, so its links are reset
+        // here instead of unlinking every tree that got linked under another.
+        for tree in buf.into_iter().flatten() {
+            let node = self.tree_mut(tree);
+            node.left = tree;
+            node.right = tree;
+
+            self.add_root(tree);
+        }
     }
 
-    fn cascading_cut(&mut self, tree_ptr: *mut Tree<T>) {
-        unsafe {
-            let tree = &mut *tree_ptr;
+    // `child` must be a root whose sibling links are about to be discarded.
+    fn link(&mut self, child: Index, parent: Index) {
+        let tree = self.tree_mut(child);
+        tree.parent = Some(parent);
+        tree.left = child;
+        tree.right = child;
+        tree.mark = false;
 
-            if let Some(parent_ptr) = tree.parent {
-                if tree.mark {
-                    self.cut(tree_ptr);
-                    self.cascading_cut(parent_ptr);
-                } else {
-                    tree.mark = true;
-                }
+        let tree = self.tree_mut(parent);
+        tree.degree += 1;
+
+        match tree.child {
+            None => tree.child = Some(child),
+            Some(first) => self.splice(first, child),
+        }
+    }
+
+    fn cut(&mut self, index: Index) {
+        let Some(parent) = self.tree(index).parent else {
+            return;
+        };
+
+        let right = self.tree(index).right;
+        let tree = self.tree_mut(parent);
+        tree.degree -= 1;
+
+        if tree.child == Some(index) {
+            tree.child = (right != index).then_some(right);
+        }
+
+        self.unlink(index);
+        self.tree_mut(index).mark = false;
+        self.add_root(index);
+    }
+
+    fn cascading_cut(&mut self, mut index: Index) {
+        while let Some(parent) = self.tree(index).parent {
+            if !self.tree(index).mark {
+                self.tree_mut(index).mark = true;
+                break;
             }
+
+            self.cut(index);
+            index = parent;
         }
     }
 }
 
-impl<T: Ord + Bounded + Clone> FromIterator<T> for FibHeap<T> {
+impl<T: Ord> Default for FibHeap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for FibHeap<T> {
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
         let mut heap = Self::new();
         heap.extend(iter);
@@ -220,12 +379,12 @@ impl<T: Ord + Bounded + Clone> FromIterator<T> for FibHeap<T> {
     }
 }
 
-impl<T: Ord + Bounded + Clone> Extend<T> for FibHeap<T> {
+impl<T: Ord> Extend<T> for FibHeap<T> {
     fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
         let iter = iter.into_iter();
 
         if let (_, Some(upr)) = iter.size_hint() {
-            self.roots.reserve(upr);
+            self.slots.reserve(upr);
         }
 
         for x in iter {
@@ -234,34 +393,41 @@ impl<T: Ord + Bounded + Clone> Extend<T> for FibHeap<T> {
     }
 }
 
-impl<T: Clone> Tree<T> {
-    fn new(item: T) -> Self {
-        return Self {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecreaseKeyError {
+    StaleHandle,
+    KeyIncreased,
+}
+
+impl Display for DecreaseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            DecreaseKeyError::StaleHandle => "handle does not name an element of this heap",
+            DecreaseKeyError::KeyIncreased => "new key is greater than the current one",
+        };
+
+        f.write_str(message)
+    }
+}
+
+impl Error for DecreaseKeyError {}
+
+impl<T> Tree<T> {
+    fn new(item: T, index: Index) -> Self {
+        Self {
             node: item,
-            children: Vec::new(),
             parent: None,
+            child: None,
+            left: index,
+            right: index,
+            degree: 0,
             mark: false,
-        };
+        }
     }
 
     fn root(&self) -> &T {
         &self.node
     }
-
-    fn degree(&self) -> usize {
-        self.children.len()
-    }
-
-    fn take(&mut self) -> Tree<T> {
-        let new_tree = Tree {
-            node: self.node.clone(),
-            children: std::mem::take(&mut self.children),
-            parent: std::mem::take(&mut self.parent),
-            mark: self.mark,
-        };
-
-        new_tree
-    }
 }
 
 #[cfg(test)]
@@ -284,15 +450,15 @@ mod tests {
         }
 
         let mut heap = FibHeap::new();
-
-        for &element in &elements {
-            heap.push(element);
-        }
+        let handles: Vec<_> = elements
+            .iter()
+            .map(|&element| heap.push_with_handle(element))
+            .collect();
 
         let new_min = heap.peek().copied().unwrap() - 1;
 
-        let min_node_ptr = heap.roots.last_mut().unwrap() as *mut Tree<u32>;
-        heap.decrease_key(min_node_ptr, new_min);
+        let min_handle = handles[heap.min.unwrap() as usize];
+        assert_eq!(heap.decrease_key(min_handle, new_min), Ok(()));
 
         assert_eq!(heap.peek(), Some(&new_min))
     }
@@ -304,15 +470,63 @@ mod tests {
         }
 
         let mut heap = FibHeap::new();
+        let handles: Vec<_> = elements
+            .iter()
+            .map(|&element| heap.push_with_handle(element))
+            .collect();
+
+        let delete_handle = handles[0];
+
+        assert!(heap.remove(delete_handle).is_some());
+
+        assert_eq!(heap.len(), elements.len() - 1)
+    }
 
-        for &element in &elements {
-            heap.push(element);
+    #[quickcheck]
+    fn links_stay_consistent(elements: Vec<u32>) {
+        let mut heap = FibHeap::new();
+        let handles: Vec<_> = elements
+            .iter()
+            .map(|&element| heap.push_with_handle(element))
+            .collect();
+
+        heap.pop();
+
+        for &handle in handles.iter().step_by(3) {
+            let _ = heap.decrease_key(handle, 0);
         }
 
-        let delete_node_ptr = heap.roots.first_mut().unwrap() as *mut Tree<u32>;
+        let Some(min) = heap.min else {
+            return;
+        };
 
-        heap.delete(delete_node_ptr);
+        let mut count = 0;
+        let mut stack = vec![(min, None)];
+        while let Some((first, parent)) = stack.pop() {
+            let mut siblings = 0;
+            let mut current = first;
+            loop {
+                let tree = heap.tree(current);
+                assert_eq!(tree.parent, parent);
+                assert_eq!(heap.tree(tree.right).left, current);
 
-        assert_eq!(heap.len(), elements.len() - 1)
+                if let Some(child) = tree.child {
+                    stack.push((child, Some(current)));
+                }
+
+                siblings += 1;
+                current = tree.right;
+                if current == first {
+                    break;
+                }
+            }
+
+            if let Some(parent) = parent {
+                assert_eq!(heap.tree(parent).degree, siblings);
+            }
+            count += siblings as usize;
+        }
+
+        assert_eq!(count, heap.len());
     }
 }
@@ -0,0 +1,52 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{SeqAccess, Visitor},
+    ser::SerializeSeq,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::FibHeap;
+
+impl<T: Serialize> Serialize for FibHeap<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len))?;
+
+        for tree in self.slots.iter().filter_map(|slot| slot.tree.as_ref()) {
+            seq.serialize_element(&tree.node)?;
+        }
+
+        seq.end()
+    }
+}
+
+impl<'de, T> Deserialize<'de> for FibHeap<T>
+where
+    T: Deserialize<'de> + Ord,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SeqVisitor<T>(PhantomData<T>);
+
+        impl<'de, T> Visitor<'de> for SeqVisitor<T>
+        where
+            T: Deserialize<'de> + Ord,
+        {
+            type Value = FibHeap<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a sequence")
+            }
+
+            fn visit_seq<S: SeqAccess<'de>>(self, mut access: S) -> Result<Self::Value, S::Error> {
+                let mut heap = FibHeap::new();
+                while let Some(item) = access.next_element()? {
+                    heap.push(item);
+                }
+
+                Ok(heap)
+            }
+        }
+
+        deserializer.deserialize_seq(SeqVisitor(PhantomData))
+    }
+}
@@ -1,10 +1,17 @@
-use std::io::{self, Write};
+use std::{
+    fs,
+    io::{self, Write},
+};
 
-use nasp_homework::np::{Graph, Statement, CNF};
+use nasp_homework::{
+    np::{Graph, Statement, CNF},
+    rbtree::RbTree,
+};
 
 fn main() {
     let mut cnf: Option<CNF> = None;
     let mut graph: Option<Graph> = None;
+    let mut tree: RbTree<i64> = RbTree::new();
 
     loop {
         println!("\nMenu:");
@@ -19,6 +26,10 @@ fn main() {
         println!("8. Verify clique");
         println!("9. Reduce 3-SAT to indset");
         println!("10. Reduce 3-SAT to clique");
+        println!("11. Insert into tree");
+        println!("12. Delete from tree");
+        println!("13. Print tree");
+        println!("14. Export tree to DOT");
         print!("\nEnter your choice: ");
 
         io::stdout().flush().unwrap();
@@ -131,6 +142,47 @@ fn main() {
                     println!("No formula found");
                 }
             }
+            "11" => {
+                for key in input_keys() {
+                    if !tree.insert(key) {
+                        println!("{} is already in the tree.", key);
+                    }
+                }
+
+                print_tree(&tree);
+            }
+            "12" => {
+                for key in input_keys() {
+                    if tree.delete(&key).is_none() {
+                        println!("{} is not in the tree.", key);
+                    }
+                }
+
+                print_tree(&tree);
+            }
+            "13" => {
+                print_tree(&tree);
+            }
+            "14" => {
+                print!("Input file name (or <Enter> to print): ");
+
+                io::stdout().flush().unwrap();
+
+                let mut path = String::new();
+
+                io::stdin().read_line(&mut path).unwrap();
+
+                let path = path.trim();
+
+                if path.is_empty() {
+                    print!("{}", tree.to_dot());
+                } else {
+                    match fs::write(path, tree.to_dot()) {
+                        Ok(()) => println!("Tree written to {}.", path),
+                        Err(error) => println!("Failed to write {}: {}", path, error),
+                    }
+                }
+            }
             _ => {
                 println!("Invalid choice. Please try again.");
             }
@@ -241,6 +293,28 @@ fn input_set() -> Vec<usize> {
         .collect()
 }
 
+fn input_keys() -> Vec<i64> {
+    print!("Input keys (e.i. '5 3 8 <Enter>'): ");
+
+    io::stdout().flush().unwrap();
+
+    let mut line = String::new();
+
+    io::stdin().read_line(&mut line).unwrap();
+
+    line.split_whitespace()
+        .map(|x| x.parse::<i64>().unwrap())
+        .collect()
+}
+
+fn print_tree(tree: &RbTree<i64>) {
+    if tree.is_empty() {
+        println!("The tree is empty.");
+    } else {
+        tree.pretty_print();
+    }
+}
+
 fn read_usize() -> usize {
     let mut input = String::new();
 
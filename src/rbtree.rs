@@ -1,4 +1,25 @@
-use std::{borrow::Borrow, cmp::Ordering, fmt::Debug, ptr::NonNull};
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    error::Error,
+    fmt::{self, Debug, Display},
+    hash::{Hash, Hasher},
+    iter::{FusedIterator, Peekable},
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+    ptr::NonNull,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+pub mod arena;
+pub mod persistent;
+#[cfg(feature = "serde")]
+mod serde;
+mod shape;
+
+pub use arena::ArenaRbTree;
+pub use persistent::PersistentRbTree;
+use shape::Shape;
 
 #[derive(Clone, Copy, Debug)]
 enum Color {
@@ -7,43 +28,54 @@ enum Color {
 }
 
 #[derive(Debug)]
-struct Node<K> {
-    parent: Link<K>,
-    left: Link<K>,
-    right: Link<K>,
+struct Node<K, V, A> {
+    parent: Link<K, V, A>,
+    left: Link<K, V, A>,
+    right: Link<K, V, A>,
     color: Color,
+    size: usize,
+    augment: A,
     key: K,
+    value: V,
 }
 
-type NodePtr<K> = NonNull<Node<K>>;
-type Link<K> = Option<NodePtr<K>>;
-type LinkPtr<K> = NonNull<Link<K>>;
+type NodePtr<K, V, A> = NonNull<Node<K, V, A>>;
+type Link<K, V, A> = Option<NodePtr<K, V, A>>;
+type LinkPtr<K, V, A> = NonNull<Link<K, V, A>>;
 
-enum InsertPos<K> {
+enum InsertPos<K, V, A> {
     Vacant {
-        parent: Link<K>,
-        link_ptr: LinkPtr<K>,
+        parent: Link<K, V, A>,
+        link_ptr: LinkPtr<K, V, A>,
+    },
+    Occupied {
+        node_ptr: NodePtr<K, V, A>,
     },
-    Occupied {},
 }
 
-impl<K: Debug> Node<K> {
-    fn create(parent: Link<K>, key: K) -> NodePtr<K> {
+impl<K, V, A> Node<K, V, A> {
+    fn create(parent: Link<K, V, A>, key: K, value: V) -> NodePtr<K, V, A>
+    where
+        A: Augment<K>,
+    {
         let boxed = Box::new(Node {
             parent,
             left: None,
             right: None,
             color: Color::Red,
+            size: 1,
+            augment: A::from_key(&key),
             key,
+            value,
         });
 
         NodePtr::from(Box::leak(boxed))
     }
 
-    unsafe fn destroy(node_ptr: NodePtr<K>) -> K {
+    unsafe fn destroy(node_ptr: NodePtr<K, V, A>) -> (K, V) {
         let boxed = Box::from_raw(node_ptr.as_ptr());
 
-        boxed.key
+        (boxed.key, boxed.value)
     }
 
     fn is_red(&self) -> bool {
@@ -54,52 +86,439 @@ impl<K: Debug> Node<K> {
     }
 
     fn is_black(&self) -> bool {
-        return !self.is_red();
-    }
-
-    fn has_child(&self) -> bool {
-        return self.left.is_some() || self.right.is_some();
+        !self.is_red()
     }
 
     fn has_left(&self) -> bool {
-        return self.left.is_some();
+        self.left.is_some()
     }
 
     fn has_right(&self) -> bool {
-        return self.right.is_some();
+        self.right.is_some()
+    }
+
+    fn size(link: Link<K, V, A>) -> usize {
+        link.map_or(0, |node_ptr| unsafe { node_ptr.as_ref().size })
+    }
+
+    unsafe fn update(mut node_ptr: NodePtr<K, V, A>)
+    where
+        A: Augment<K>,
+    {
+        let node = node_ptr.as_mut();
+        node.size = Self::size(node.left) + Self::size(node.right) + 1;
+
+        let mut augment = A::from_key(&node.key);
+        if let Some(left_ptr) = node.left {
+            augment = left_ptr.as_ref().augment.combine(&augment);
+        }
+        if let Some(right_ptr) = node.right {
+            augment = augment.combine(&right_ptr.as_ref().augment);
+        }
+        node.augment = augment;
+    }
+
+    fn black_height(mut link: Link<K, V, A>) -> usize {
+        let mut height = 0;
+        while let Some(node_ptr) = link {
+            unsafe {
+                if node_ptr.as_ref().is_black() {
+                    height += 1;
+                }
+                link = node_ptr.as_ref().left;
+            }
+        }
+        height
+    }
+
+    unsafe fn update_upwards(mut link: Link<K, V, A>)
+    where
+        A: Augment<K>,
+    {
+        while let Some(node_ptr) = link {
+            Self::update(node_ptr);
+            link = node_ptr.as_ref().parent;
+        }
+    }
+
+    unsafe fn minimum(node_ptr: NodePtr<K, V, A>) -> NodePtr<K, V, A> {
+        shape::minimum(Links::new(), node_ptr)
+    }
+
+    unsafe fn maximum(node_ptr: NodePtr<K, V, A>) -> NodePtr<K, V, A> {
+        shape::maximum(Links::new(), node_ptr)
+    }
+
+    unsafe fn successor(node_ptr: NodePtr<K, V, A>) -> Link<K, V, A> {
+        shape::successor(Links::new(), node_ptr)
+    }
+
+    unsafe fn predecessor(node_ptr: NodePtr<K, V, A>) -> Link<K, V, A> {
+        shape::predecessor(Links::new(), node_ptr)
     }
 }
 
-#[derive(Debug)]
-pub struct RbTree<K> {
-    root: Link<K>,
+// Reads nodes through their raw links for the shared code in `shape`.
+struct Links<'a, K, V, A>(PhantomData<&'a Node<K, V, A>>);
+
+impl<K, V, A> Links<'_, K, V, A> {
+    // SAFETY: the caller guarantees that every node reached through the
+    // returned value stays alive and unmodified for its lifetime.
+    unsafe fn new() -> Self {
+        Links(PhantomData)
+    }
 }
 
-#[allow(dead_code)]
-impl<K> RbTree<K>
-where
-    K: Copy + Debug,
-{
+impl<K, V, A> Clone for Links<'_, K, V, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V, A> Copy for Links<'_, K, V, A> {}
+
+impl<'a, K, V, A> Shape<'a> for Links<'a, K, V, A> {
+    type Node = NodePtr<K, V, A>;
+    type Key = K;
+
+    fn left(self, node_ptr: Self::Node) -> Option<Self::Node> {
+        unsafe { node_ptr.as_ref().left }
+    }
+
+    fn right(self, node_ptr: Self::Node) -> Option<Self::Node> {
+        unsafe { node_ptr.as_ref().right }
+    }
+
+    fn parent(self, node_ptr: Self::Node) -> Option<Self::Node> {
+        unsafe { node_ptr.as_ref().parent }
+    }
+
+    fn key(self, node_ptr: Self::Node) -> &'a K {
+        unsafe { &(*node_ptr.as_ptr()).key }
+    }
+
+    fn is_red(self, node_ptr: Self::Node) -> bool {
+        unsafe { node_ptr.as_ref().is_red() }
+    }
+}
+
+pub trait Augment<K> {
+    fn from_key(key: &K) -> Self;
+
+    fn combine(&self, other: &Self) -> Self;
+}
+
+impl<K> Augment<K> for () {
+    fn from_key(_: &K) -> Self {}
+
+    fn combine(&self, _: &Self) -> Self {}
+}
+
+pub trait Comparator<T: ?Sized> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Natural;
+
+impl<T: Ord + ?Sized> Comparator<T> for Natural {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FnComparator<F>(pub F);
+
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Comparator<T> for FnComparator<F> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a, b)
+    }
+}
+
+impl<T: ?Sized, C: Comparator<T> + ?Sized> Comparator<T> for Box<C> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (**self).compare(a, b)
+    }
+}
+
+impl<T: ?Sized, C: Comparator<T> + ?Sized> Comparator<T> for Arc<C> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (**self).compare(a, b)
+    }
+}
+
+pub struct RbMap<K, V, A = (), C = Natural> {
+    root: Link<K, V, A>,
+    len: usize,
+    comparator: C,
+}
+
+pub struct RbTree<K, A = (), C = Natural> {
+    map: RbMap<K, (), A, C>,
+}
+
+impl<K, V> RbMap<K, V> {
     pub fn new() -> Self
     where
         K: Ord,
     {
-        Self { root: None }
+        Self::with_comparator(Natural)
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.root.is_none()
+    pub fn from_sorted_iter<I>(iter: I) -> Self
+    where
+        K: Ord,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut entries: Vec<(K, V)> = iter.into_iter().collect();
+
+        assert!(
+            entries.is_sorted_by(|a, b| a.0 <= b.0),
+            "from_sorted_iter requires keys in ascending order"
+        );
+
+        Self::dedup_sorted(&mut entries, &Natural);
+        Self::from_sorted_vec(entries, Natural)
+    }
+}
+
+impl<K, V, C: Comparator<K>> RbMap<K, V, (), C> {
+    pub fn with_comparator(comparator: C) -> Self {
+        Self {
+            root: None,
+            len: 0,
+            comparator,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl<K, V, A: Augment<K>, C: Comparator<K>> RbMap<K, V, A, C> {
+    fn dedup_sorted(entries: &mut Vec<(K, V)>, comparator: &C) {
+        entries.dedup_by(|later, earlier| {
+            if comparator.compare(&later.0, &earlier.0).is_eq() {
+                std::mem::swap(&mut later.1, &mut earlier.1);
+                true
+            } else {
+                false
+            }
+        });
+    }
+
+    fn from_sorted_vec(entries: Vec<(K, V)>, comparator: C) -> Self {
+        let len = entries.len();
+        if len == 0 {
+            return Self {
+                root: None,
+                len: 0,
+                comparator,
+            };
+        }
+
+        let red_depth = len.ilog2() as usize;
+        let mut entries = entries.into_iter();
+        let root = unsafe { Self::build_sorted(&mut entries, len, 0, red_depth) };
+
+        Self {
+            root,
+            len,
+            comparator,
+        }
+    }
+
+    fn empty(&self) -> Self
+    where
+        C: Clone,
+    {
+        Self {
+            root: None,
+            len: 0,
+            comparator: self.comparator.clone(),
+        }
     }
 
-    fn find<Q>(&self, key: &Q) -> Link<K>
+    unsafe fn build_sorted(
+        entries: &mut impl Iterator<Item = (K, V)>,
+        len: usize,
+        depth: usize,
+        red_depth: usize,
+    ) -> Link<K, V, A> {
+        if len == 0 {
+            return None;
+        }
+
+        let left_len = len / 2;
+        let left = Self::build_sorted(entries, left_len, depth + 1, red_depth);
+
+        let (key, value) = entries.next().unwrap();
+        let mut node_ptr = Node::create(None, key, value);
+
+        let right = Self::build_sorted(entries, len - left_len - 1, depth + 1, red_depth);
+
+        let node = node_ptr.as_mut();
+        node.left = left;
+        node.right = right;
+        node.color = if depth == red_depth && depth > 0 {
+            Color::Red
+        } else {
+            Color::Black
+        };
+
+        for mut child_ptr in [left, right].into_iter().flatten() {
+            child_ptr.as_mut().parent = Some(node_ptr);
+        }
+        Node::update(node_ptr);
+
+        Some(node_ptr)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.find(key)
+            .map(|node_ptr| unsafe { &(*node_ptr.as_ptr()).value })
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.find(key)
+            .map(|node_ptr| unsafe { &mut (*node_ptr.as_ptr()).value })
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        let link = self.root.map(|root_ptr| unsafe { Node::minimum(root_ptr) });
+
+        self.key_value(link)
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        let link = self.root.map(|root_ptr| unsafe { Node::maximum(root_ptr) });
+
+        self.key_value(link)
+    }
+
+    pub fn floor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.key_value(self.find_upper_bound(Bound::Included(key)))
+    }
+
+    pub fn ceiling<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.key_value(self.find_lower_bound(Bound::Included(key)))
+    }
+
+    pub fn predecessor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.key_value(self.find_upper_bound(Bound::Excluded(key)))
+    }
+
+    pub fn successor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.key_value(self.find_lower_bound(Bound::Excluded(key)))
+    }
+
+    fn key_value(&self, link: Link<K, V, A>) -> Option<(&K, &V)> {
+        link.map(|node_ptr| unsafe {
+            let node = &*node_ptr.as_ptr();
+            (&node.key, &node.value)
+        })
+    }
+
+    pub fn select(&self, index: usize) -> Option<(&K, &V)> {
+        if index >= self.len {
+            return None;
+        }
+
+        let mut index = index;
+        let mut current = self.root;
+        while let Some(node_ptr) = current {
+            unsafe {
+                let left_size = Node::size(node_ptr.as_ref().left);
+
+                match index.cmp(&left_size) {
+                    Ordering::Less => current = node_ptr.as_ref().left,
+                    Ordering::Equal => {
+                        let node = &*node_ptr.as_ptr();
+                        return Some((&node.key, &node.value));
+                    }
+                    Ordering::Greater => {
+                        index -= left_size + 1;
+                        current = node_ptr.as_ref().right;
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut rank = 0;
+        let mut current = self.root;
+        while let Some(node_ptr) = current {
+            unsafe {
+                if self
+                    .comparator
+                    .compare(key, node_ptr.as_ref().key.borrow())
+                    .is_le()
+                {
+                    current = node_ptr.as_ref().left;
+                } else {
+                    rank += Node::size(node_ptr.as_ref().left) + 1;
+                    current = node_ptr.as_ref().right;
+                }
+            }
+        }
+        rank
+    }
+
+    fn find<Q>(&self, key: &Q) -> Link<K, V, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
     {
         let mut current = self.root;
         while let Some(node_ptr) = current {
             current = unsafe {
-                match key.cmp(node_ptr.as_ref().key.borrow()) {
+                match self.comparator.compare(key, node_ptr.as_ref().key.borrow()) {
                     Ordering::Equal => break,
                     Ordering::Less => node_ptr.as_ref().left,
                     Ordering::Greater => node_ptr.as_ref().right,
@@ -109,66 +528,185 @@ where
         current
     }
 
-    pub fn clear(&mut self) {
-        self.recursive_destroy(self.root);
+    // The borrowed-key counterpart of entry for callers that only need an
+    // existing entry and cannot give up an owned key.
+    fn find_entry<Q>(&mut self, key: &Q) -> Option<OccupiedEntry<'_, K, V, A, C>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let node_ptr = self.find(key)?;
 
-        self.root = None;
+        Some(OccupiedEntry {
+            map: self,
+            node_ptr,
+        })
     }
 
-    fn recursive_destroy(&mut self, node: Link<K>) {
-        match node {
-            None => return,
-            Some(mut node_ptr) => unsafe {
-                self.recursive_destroy(node_ptr.as_mut().left);
-                self.recursive_destroy(node_ptr.as_mut().right);
-                Node::destroy(node_ptr);
-            },
+    pub fn range<Q, R>(&self, range: R) -> MapRange<'_, K, V, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+    {
+        match (range.start_bound(), range.end_bound()) {
+            (Bound::Excluded(start), Bound::Excluded(end))
+                if self.comparator.compare(start, end).is_eq() =>
+            {
+                panic!("range start and end are equal and excluded")
+            }
+            (
+                Bound::Included(start) | Bound::Excluded(start),
+                Bound::Included(end) | Bound::Excluded(end),
+            ) if self.comparator.compare(start, end).is_gt() => {
+                panic!("range start is greater than range end")
+            }
+            _ => (),
         }
-    }
 
-    pub fn inorder_traverse(&self) -> Vec<K> {
-        let mut result: Vec<K> = Vec::new();
+        let mut front = self.find_lower_bound(range.start_bound());
+        let mut back = self.find_upper_bound(range.end_bound());
+
+        if let (Some(front_ptr), Some(back_ptr)) = (front, back) {
+            unsafe {
+                let front_key = front_ptr.as_ref().key.borrow();
+                if self
+                    .comparator
+                    .compare(front_key, back_ptr.as_ref().key.borrow())
+                    .is_gt()
+                {
+                    front = None;
+                    back = None;
+                }
+            }
+        } else {
+            front = None;
+            back = None;
+        }
 
-        self.inorder(self.root.borrow(), &mut result);
+        MapRange {
+            front,
+            back,
+            marker: PhantomData,
+        }
+    }
 
+    fn find_lower_bound<Q>(&self, bound: Bound<&Q>) -> Link<K, V, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut result = None;
+        let mut current = self.root;
+        while let Some(node_ptr) = current {
+            unsafe {
+                if self.above_lower(node_ptr.as_ref().key.borrow(), bound) {
+                    result = current;
+                    current = node_ptr.as_ref().left;
+                } else {
+                    current = node_ptr.as_ref().right;
+                }
+            }
+        }
         result
     }
 
-    fn inorder(&self, node: &Link<K>, result: &mut Vec<K>) {
-        match node {
-            None => return,
-            Some(node_ptr) => unsafe {
-                let key = node_ptr.as_ref().key;
-
-                self.inorder(node_ptr.as_ref().left.borrow(), result);
-                result.push(key);
-                self.inorder(node_ptr.as_ref().right.borrow(), result);
-            },
+    fn find_upper_bound<Q>(&self, bound: Bound<&Q>) -> Link<K, V, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut result = None;
+        let mut current = self.root;
+        while let Some(node_ptr) = current {
+            unsafe {
+                if self.below_upper(node_ptr.as_ref().key.borrow(), bound) {
+                    result = current;
+                    current = node_ptr.as_ref().right;
+                } else {
+                    current = node_ptr.as_ref().left;
+                }
+            }
         }
+        result
     }
 
-    pub fn preorder_tranverse(&self) -> Vec<K> {
-        let mut result: Vec<K> = Vec::new();
+    pub fn range_aggregate<Q, R>(&self, range: R) -> Option<A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+        A: Clone,
+    {
+        unsafe { self.aggregate(self.root, range.start_bound(), range.end_bound()) }
+    }
 
-        self.preorder(self.root.borrow(), &mut result);
+    fn above_lower<Q>(&self, key: &Q, bound: Bound<&Q>) -> bool
+    where
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        match bound {
+            Bound::Included(bound_key) => self.comparator.compare(key, bound_key).is_ge(),
+            Bound::Excluded(bound_key) => self.comparator.compare(key, bound_key).is_gt(),
+            Bound::Unbounded => true,
+        }
+    }
 
-        result
+    fn below_upper<Q>(&self, key: &Q, bound: Bound<&Q>) -> bool
+    where
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        match bound {
+            Bound::Included(bound_key) => self.comparator.compare(key, bound_key).is_le(),
+            Bound::Excluded(bound_key) => self.comparator.compare(key, bound_key).is_lt(),
+            Bound::Unbounded => true,
+        }
     }
 
-    fn preorder(&self, node: &Link<K>, result: &mut Vec<K>) {
-        match node {
-            None => return,
-            Some(node_ptr) => unsafe {
-                let key = node_ptr.as_ref().key;
+    unsafe fn aggregate<Q>(
+        &self,
+        link: Link<K, V, A>,
+        lower: Bound<&Q>,
+        upper: Bound<&Q>,
+    ) -> Option<A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        A: Clone,
+    {
+        let node = link?.as_ref();
+
+        if let (Bound::Unbounded, Bound::Unbounded) = (lower, upper) {
+            return Some(node.augment.clone());
+        }
 
-                result.push(key);
-                self.preorder(node_ptr.as_ref().left.borrow(), result);
-                self.preorder(node_ptr.as_ref().right.borrow(), result);
-            },
+        if !self.above_lower(node.key.borrow(), lower) {
+            return self.aggregate(node.right, lower, upper);
         }
+        if !self.below_upper(node.key.borrow(), upper) {
+            return self.aggregate(node.left, lower, upper);
+        }
+
+        let mut augment = A::from_key(&node.key);
+        if let Some(left) = self.aggregate(node.left, lower, Bound::Unbounded) {
+            augment = left.combine(&augment);
+        }
+        if let Some(right) = self.aggregate(node.right, Bound::Unbounded, upper) {
+            augment = augment.combine(&right);
+        }
+
+        Some(augment)
     }
 
-    fn rotate_left(&mut self, mut node_ptr: NodePtr<K>) {
+    fn rotate_left(&mut self, mut node_ptr: NodePtr<K, V, A>) {
         unsafe {
             if let Some(mut right_ptr) = node_ptr.as_ref().right {
                 node_ptr.as_mut().right = right_ptr.as_ref().left;
@@ -190,11 +728,14 @@ where
 
                 right_ptr.as_mut().left = Some(node_ptr);
                 node_ptr.as_mut().parent = Some(right_ptr);
+
+                Node::update(node_ptr);
+                Node::update(right_ptr);
             }
         }
     }
 
-    fn rotate_right(&mut self, mut node_ptr: NodePtr<K>) {
+    fn rotate_right(&mut self, mut node_ptr: NodePtr<K, V, A>) {
         unsafe {
             if let Some(mut left_ptr) = node_ptr.as_ref().left {
                 node_ptr.as_mut().left = left_ptr.as_ref().right;
@@ -216,40 +757,59 @@ where
 
                 left_ptr.as_mut().right = Some(node_ptr);
                 node_ptr.as_mut().parent = Some(left_ptr);
+
+                Node::update(node_ptr);
+                Node::update(left_ptr);
             }
         }
     }
 
-    pub fn insert(&mut self, key: K) -> bool
-    where
-        K: Ord,
-    {
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
         match self.find_insert_pos(&key) {
             InsertPos::Vacant { parent, link_ptr } => unsafe {
-                self.insert_entry_at_pos(parent, link_ptr, key);
-                true
+                self.insert_entry_at_pos(parent, link_ptr, key, value);
+                None
+            },
+            InsertPos::Occupied { mut node_ptr } => unsafe {
+                Some(std::mem::replace(&mut node_ptr.as_mut().value, value))
             },
-            InsertPos::Occupied {} => false,
         }
     }
 
-    fn find_insert_pos<Q>(&mut self, key: &Q) -> InsertPos<K>
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, A, C> {
+        match self.find_insert_pos(&key) {
+            InsertPos::Vacant { parent, link_ptr } => Entry::Vacant(VacantEntry {
+                map: self,
+                key,
+                parent,
+                link_ptr,
+            }),
+            InsertPos::Occupied { node_ptr } => Entry::Occupied(OccupiedEntry {
+                map: self,
+                node_ptr,
+            }),
+        }
+    }
+
+    fn find_insert_pos<Q>(&mut self, key: &Q) -> InsertPos<K, V, A>
     where
         K: Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Comparator<Q>,
     {
-        let mut parent: Link<K> = None;
-        let mut link_ptr: LinkPtr<K> = unsafe { LinkPtr::new_unchecked(&mut self.root) };
+        let mut parent: Link<K, V, A> = None;
+        let mut link_ptr: LinkPtr<K, V, A> = unsafe { LinkPtr::new_unchecked(&mut self.root) };
 
         unsafe {
             while let Some(mut node_ptr) = link_ptr.as_ref() {
-                if key == node_ptr.as_ref().key.borrow() {
-                    return InsertPos::Occupied {};
-                } else {
-                    parent = *link_ptr.as_ref();
-                    if key < node_ptr.as_ref().key.borrow() {
+                match self.comparator.compare(key, node_ptr.as_ref().key.borrow()) {
+                    Ordering::Equal => return InsertPos::Occupied { node_ptr },
+                    Ordering::Less => {
+                        parent = *link_ptr.as_ref();
                         link_ptr = LinkPtr::new_unchecked(&mut node_ptr.as_mut().left);
-                    } else {
+                    }
+                    Ordering::Greater => {
+                        parent = *link_ptr.as_ref();
                         link_ptr = LinkPtr::new_unchecked(&mut node_ptr.as_mut().right);
                     }
                 }
@@ -259,15 +819,26 @@ where
         InsertPos::Vacant { parent, link_ptr }
     }
 
-    unsafe fn insert_entry_at_pos(&mut self, parent: Link<K>, mut insert_pos: LinkPtr<K>, key: K) {
-        let node_ptr = Node::create(parent, key);
+    unsafe fn insert_entry_at_pos(
+        &mut self,
+        parent: Link<K, V, A>,
+        mut insert_pos: LinkPtr<K, V, A>,
+        key: K,
+        value: V,
+    ) -> NodePtr<K, V, A> {
+        let node_ptr = Node::create(parent, key, value);
 
         *insert_pos.as_mut() = Some(node_ptr);
+        self.len += 1;
+
+        Node::update_upwards(parent);
 
         self.balance_insert(node_ptr);
+
+        node_ptr
     }
 
-    fn balance_insert(&mut self, start_from: NodePtr<K>) {
+    fn balance_insert(&mut self, start_from: NodePtr<K, V, A>) -> bool {
         let mut current = Some(start_from);
 
         unsafe {
@@ -322,58 +893,105 @@ where
                 }
             }
 
-            if let Some(mut root_ptr) = self.root {
-                root_ptr.as_mut().color = Color::Black;
+            match self.root {
+                Some(mut root_ptr) if root_ptr.as_ref().is_red() => {
+                    root_ptr.as_mut().color = Color::Black;
+                    true
+                }
+                _ => false,
             }
         }
     }
 
-    pub fn delete<Q>(&mut self, key: &Q) -> Option<K>
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
     where
         K: Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Comparator<Q>,
     {
-        let found = self.find(key);
+        self.remove_entry(key).map(|(_, value)| value)
+    }
 
-        if found.is_none() {
-            return None;
-        }
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let node_ptr = self.find(key)?;
+
+        Some(unsafe { self.remove_entry_at_occupied_pos(node_ptr) })
+    }
+
+    pub fn retain<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.extract_if(|key, value| !pred(key, value))
+            .for_each(drop);
+    }
 
-        let node_ptr = found.unwrap();
-        let key = unsafe { self.remove_entry_at_occupied_pos(node_ptr) };
+    pub fn extract_if<F>(&mut self, pred: F) -> MapExtractIf<'_, K, V, F, A, C>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let next = self.root.map(|root_ptr| unsafe { Node::minimum(root_ptr) });
 
-        Some(key)
+        MapExtractIf {
+            map: self,
+            next,
+            pred,
+        }
+    }
+
+    unsafe fn extract_next<F>(&mut self, next: &mut Link<K, V, A>, mut pred: F) -> Option<(K, V)>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        while let Some(node_ptr) = *next {
+            // Removal relinks nodes instead of moving entries between them,
+            // so the successor stays valid after node_ptr is taken out.
+            *next = Node::successor(node_ptr);
+
+            let node = &mut *node_ptr.as_ptr();
+            if pred(&node.key, &mut node.value) {
+                return Some(self.remove_entry_at_occupied_pos(node_ptr));
+            }
+        }
+        None
     }
 
-    unsafe fn remove_entry_at_occupied_pos(&mut self, mut node_ptr: NodePtr<K>) -> K {
+    unsafe fn remove_entry_at_occupied_pos(&mut self, mut node_ptr: NodePtr<K, V, A>) -> (K, V) {
         debug_assert!(!self.is_empty());
 
         let mut is_min_child_black = node_ptr.as_ref().is_black();
-        let replacement: Option<NonNull<Node<K>>>;
-
-        let mut leaf_deletion = false;
+        let replacement: Link<K, V, A>;
+        let replacement_parent: Link<K, V, A>;
 
-        if !node_ptr.as_ref().has_child() {
-            replacement = Some(node_ptr);
-            leaf_deletion = true;
-        } else if !node_ptr.as_ref().has_left() {
+        if !node_ptr.as_ref().has_left() {
             replacement = node_ptr.as_mut().right;
+            replacement_parent = node_ptr.as_ref().parent;
             self.transplant(node_ptr, node_ptr.as_mut().right);
         } else if !node_ptr.as_ref().has_right() {
             replacement = node_ptr.as_mut().left;
+            replacement_parent = node_ptr.as_ref().parent;
             self.transplant(node_ptr, node_ptr.as_mut().left);
         } else {
-            let mut min_child_ptr = self.minimum(node_ptr.as_ref().right.unwrap());
+            let mut min_child_ptr = Node::minimum(node_ptr.as_ref().right.unwrap());
             is_min_child_black = min_child_ptr.as_ref().is_black();
             replacement = min_child_ptr.as_mut().right;
 
             if Some(min_child_ptr) != node_ptr.as_mut().right {
+                replacement_parent = min_child_ptr.as_ref().parent;
                 self.transplant(min_child_ptr, min_child_ptr.as_mut().right);
 
                 min_child_ptr.as_mut().right = node_ptr.as_ref().right;
                 min_child_ptr.as_mut().right.unwrap().as_mut().parent = Some(min_child_ptr);
-            } else if let Some(mut replacement_ptr) = replacement {
-                replacement_ptr.as_mut().parent = Some(min_child_ptr);
+            } else {
+                replacement_parent = Some(min_child_ptr);
+                if let Some(mut replacement_ptr) = replacement {
+                    replacement_ptr.as_mut().parent = Some(min_child_ptr);
+                }
             }
 
             self.transplant(node_ptr, Some(min_child_ptr));
@@ -382,28 +1000,377 @@ where
             min_child_ptr.as_mut().color = node_ptr.as_ref().color;
         }
 
+        Node::update_upwards(replacement_parent);
+
         if is_min_child_black {
-            self.balance_delete(replacement);
+            self.balance_delete(replacement, replacement_parent);
         }
 
-        if leaf_deletion {
-            self.transplant(node_ptr, None);
-        }
+        self.len -= 1;
 
         Node::destroy(node_ptr)
     }
 
-    fn minimum(&self, node_ptr: NodePtr<K>) -> NodePtr<K> {
+    pub fn split_off<Q>(&mut self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q> + Clone,
+    {
+        let height = Node::black_height(self.root);
+        let root = self.root.take();
+        let (tree, height) = self.fragment(root, height);
+        self.len = 0;
+
+        let mut split = unsafe { Self::split_tree(tree, height, key) };
+        *self = split.left;
+
+        if let Some(found_ptr) = split.found {
+            let mut empty = self.empty();
+            unsafe {
+                empty.join(0, found_ptr, split.right, split.right_height);
+            }
+            split.right = empty;
+        }
+
+        split.right
+    }
+
+    // `other` is spliced in by `self`'s comparator, so it must already be
+    // sorted by an equivalent one.
+    pub fn append(&mut self, other: &mut Self)
+    where
+        C: Clone,
+    {
+        self.debug_assert_same_order(other);
+
+        if other.is_empty() {
+            return;
+        }
+
+        if self.is_empty() {
+            std::mem::swap(self, other);
+            return;
+        }
+
+        if other.precedes(self) {
+            std::mem::swap(self, other);
+        }
+
         unsafe {
-            if !node_ptr.as_ref().has_left() {
-                return node_ptr;
+            if self.precedes(other) {
+                let min_ptr = Node::minimum(other.root.unwrap());
+                let (key, value) = other.remove_entry_at_occupied_pos(min_ptr);
+                let middle_ptr = Node::create(None, key, value);
+
+                let height = Node::black_height(self.root);
+                let other_height = Node::black_height(other.root);
+                let right = std::mem::replace(other, self.empty());
+
+                self.join(height, middle_ptr, right, other_height);
+            } else {
+                while let Some(root_ptr) = other.root {
+                    let min_ptr = Node::minimum(root_ptr);
+                    let (key, value) = other.remove_entry_at_occupied_pos(min_ptr);
+
+                    self.insert(key, value);
+                }
+            }
+        }
+    }
+
+    fn debug_assert_same_order(&self, other: &Self) {
+        debug_assert!(
+            other
+                .iter()
+                .map(|(key, _)| key)
+                .is_sorted_by(|a, b| self.comparator.compare(a, b).is_lt()),
+            "the other map is not sorted by this map's comparator"
+        );
+    }
+
+    fn precedes(&self, other: &Self) -> bool {
+        match (self.last(), other.first()) {
+            (Some((last, _)), Some((first, _))) => self.comparator.compare(last, first).is_lt(),
+            _ => false,
+        }
+    }
+
+    // Like append, this splits `other` by `self`'s comparator, so both maps
+    // must order their keys the same way.
+    pub fn union_with(&mut self, other: Self)
+    where
+        C: Clone,
+    {
+        self.merge_with(other, SetOp::Union);
+    }
+
+    // Keys of `other` are looked up by `self`'s comparator.
+    pub fn intersect_with(&mut self, other: Self)
+    where
+        C: Clone,
+    {
+        self.merge_with(other, SetOp::Intersection);
+    }
+
+    // Keys of `other` are looked up by `self`'s comparator.
+    pub fn difference_with(&mut self, other: Self)
+    where
+        C: Clone,
+    {
+        self.merge_with(other, SetOp::Difference);
+    }
+
+    // Keys kept from `other` are placed by `self`'s comparator, so it must
+    // agree with the one `other` was built with.
+    pub fn symmetric_difference_with(&mut self, other: Self)
+    where
+        C: Clone,
+    {
+        self.merge_with(other, SetOp::SymmetricDifference);
+    }
+
+    fn merge_with(&mut self, mut other: Self, op: SetOp)
+    where
+        C: Clone,
+    {
+        self.debug_assert_same_order(&other);
+
+        let height = Node::black_height(self.root);
+        let other_height = Node::black_height(other.root);
+        let root = self.root.take();
+        let (tree, height) = self.fragment(root, height);
+        let (other, other_height) = self.fragment(other.root.take(), other_height);
+        self.len = 0;
+
+        let (merged, _) = unsafe { Self::merge_tree(tree, height, other, other_height, op) };
+        *self = merged;
+    }
+
+    unsafe fn merge_tree(
+        mut tree: Self,
+        height: usize,
+        mut other: Self,
+        other_height: usize,
+        op: SetOp,
+    ) -> (Self, usize)
+    where
+        C: Clone,
+    {
+        let Some(node_ptr) = tree.root.take() else {
+            return match op {
+                SetOp::Union | SetOp::SymmetricDifference => (other, other_height),
+                SetOp::Intersection | SetOp::Difference => {
+                    other.clear();
+                    (other, 0)
+                }
+            };
+        };
+
+        if other.is_empty() {
+            tree.root = Some(node_ptr);
+            return match op {
+                SetOp::Union | SetOp::Difference | SetOp::SymmetricDifference => (tree, height),
+                SetOp::Intersection => {
+                    tree.clear();
+                    (tree, 0)
+                }
+            };
+        }
+
+        let (left, left_height) = tree.fragment(node_ptr.as_ref().left, height - 1);
+        let (right, right_height) = tree.fragment(node_ptr.as_ref().right, height - 1);
+
+        let split = Self::split_tree(other, other_height, &node_ptr.as_ref().key);
+        let found = split.found.is_some();
+        if let Some(found_ptr) = split.found {
+            Node::destroy(found_ptr);
+        }
+
+        let (mut left, left_height) =
+            Self::merge_tree(left, left_height, split.left, split.left_height, op);
+        let (right, right_height) =
+            Self::merge_tree(right, right_height, split.right, split.right_height, op);
+
+        let keep = match op {
+            SetOp::Union => true,
+            SetOp::Intersection => found,
+            SetOp::Difference | SetOp::SymmetricDifference => !found,
+        };
+
+        if keep {
+            let height = left.join(left_height, node_ptr, right, right_height);
+            (left, height)
+        } else {
+            Node::destroy(node_ptr);
+            let height = left.concat(left_height, right);
+            (left, height)
+        }
+    }
+
+    unsafe fn concat(&mut self, height: usize, mut right: Self) -> usize {
+        let Some(right_root) = right.root else {
+            return height;
+        };
+
+        let min_ptr = Node::minimum(right_root);
+        let (key, value) = right.remove_entry_at_occupied_pos(min_ptr);
+        let middle_ptr = Node::create(None, key, value);
+
+        let right_height = Node::black_height(right.root);
+
+        self.join(height, middle_ptr, right, right_height)
+    }
+
+    fn fragment(&self, link: Link<K, V, A>, mut height: usize) -> (Self, usize)
+    where
+        C: Clone,
+    {
+        if let Some(mut node_ptr) = link {
+            unsafe {
+                node_ptr.as_mut().parent = None;
+                if node_ptr.as_ref().is_red() {
+                    node_ptr.as_mut().color = Color::Black;
+                    height += 1;
+                }
+            }
+        }
+
+        let tree = Self {
+            root: link,
+            len: Node::size(link),
+            comparator: self.comparator.clone(),
+        };
+
+        (tree, height)
+    }
+
+    unsafe fn split_tree<Q>(mut tree: Self, height: usize, key: &Q) -> Split<K, V, A, C>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q> + Clone,
+    {
+        let Some(node_ptr) = tree.root.take() else {
+            return Split {
+                left: tree.empty(),
+                left_height: 0,
+                found: None,
+                right: tree.empty(),
+                right_height: 0,
+            };
+        };
+
+        let (mut left, left_height) = tree.fragment(node_ptr.as_ref().left, height - 1);
+        let (right, right_height) = tree.fragment(node_ptr.as_ref().right, height - 1);
+
+        match tree.comparator.compare(key, node_ptr.as_ref().key.borrow()) {
+            Ordering::Less => {
+                let mut split = Self::split_tree(left, left_height, key);
+                split.right_height =
+                    split
+                        .right
+                        .join(split.right_height, node_ptr, right, right_height);
+
+                split
+            }
+            Ordering::Equal => Split {
+                left,
+                left_height,
+                found: Some(node_ptr),
+                right,
+                right_height,
+            },
+            Ordering::Greater => {
+                let split = Self::split_tree(right, right_height, key);
+                let left_height = left.join(left_height, node_ptr, split.left, split.left_height);
+
+                Split {
+                    left,
+                    left_height,
+                    ..split
+                }
+            }
+        }
+    }
+
+    unsafe fn join(
+        &mut self,
+        height: usize,
+        mut middle_ptr: NodePtr<K, V, A>,
+        mut right: Self,
+        right_height: usize,
+    ) -> usize {
+        let right_root = right.root.take();
+        let mut parent: Link<K, V, A> = None;
+        let mut current: Link<K, V, A>;
+        let mut current_height: usize;
+
+        middle_ptr.as_mut().color = Color::Red;
+
+        if height >= right_height {
+            current = self.root;
+            current_height = height;
+            while current_height != right_height
+                || current.is_some_and(|node_ptr| node_ptr.as_ref().is_red())
+            {
+                let node_ptr = current.unwrap();
+                if node_ptr.as_ref().is_black() {
+                    current_height -= 1;
+                }
+                parent = current;
+                current = node_ptr.as_ref().right;
             }
 
-            self.minimum(node_ptr.as_ref().left.unwrap())
+            middle_ptr.as_mut().left = current;
+            middle_ptr.as_mut().right = right_root;
+            match parent {
+                None => self.root = Some(middle_ptr),
+                Some(mut parent_ptr) => parent_ptr.as_mut().right = Some(middle_ptr),
+            }
+        } else {
+            current = right_root;
+            current_height = right_height;
+            while current_height != height
+                || current.is_some_and(|node_ptr| node_ptr.as_ref().is_red())
+            {
+                let node_ptr = current.unwrap();
+                if node_ptr.as_ref().is_black() {
+                    current_height -= 1;
+                }
+                parent = current;
+                current = node_ptr.as_ref().left;
+            }
+
+            middle_ptr.as_mut().left = self.root;
+            middle_ptr.as_mut().right = current;
+            match parent {
+                None => self.root = Some(middle_ptr),
+                Some(mut parent_ptr) => {
+                    parent_ptr.as_mut().left = Some(middle_ptr);
+                    self.root = right_root;
+                }
+            }
         }
+
+        middle_ptr.as_mut().parent = parent;
+        if let Some(mut left_ptr) = middle_ptr.as_ref().left {
+            left_ptr.as_mut().parent = Some(middle_ptr);
+        }
+        if let Some(mut right_ptr) = middle_ptr.as_ref().right {
+            right_ptr.as_mut().parent = Some(middle_ptr);
+        }
+
+        Node::update(middle_ptr);
+        Node::update_upwards(parent);
+
+        let grew = self.balance_insert(middle_ptr);
+        self.len = Node::size(self.root);
+
+        height.max(right_height) + grew as usize
     }
 
-    unsafe fn transplant(&mut self, mut node_ptr: NodePtr<K>, replacement: Link<K>) {
+    unsafe fn transplant(&mut self, mut node_ptr: NodePtr<K, V, A>, replacement: Link<K, V, A>) {
         if node_ptr.as_ref().parent.is_none() {
             self.root = replacement;
         } else if Some(node_ptr) == node_ptr.as_mut().parent.unwrap().as_ref().left {
@@ -417,15 +1384,11 @@ where
         }
     }
 
-    unsafe fn balance_delete(&mut self, mut link: Link<K>) {
-        debug_assert!(link.is_some());
-
-        while link != self.root && link.unwrap().as_ref().is_black() {
-            let mut node_ptr = link.unwrap();
-
+    unsafe fn balance_delete(&mut self, mut link: Link<K, V, A>, mut parent: Link<K, V, A>) {
+        while link != self.root && link.is_none_or(|node_ptr| node_ptr.as_ref().is_black()) {
             macro_rules! parent_ptr {
                 () => {
-                    node_ptr.as_mut().parent.unwrap()
+                    parent.unwrap()
                 };
             }
             macro_rules! left_sibling {
@@ -441,12 +1404,6 @@ where
 
             let is_left_child: bool = link == left_sibling!();
 
-            println!(
-                "{} {}",
-                right_sibling!().is_some(),
-                left_sibling!().is_some()
-            );
-
             let mut sibling_ptr = if is_left_child {
                 right_sibling!().unwrap()
             } else {
@@ -494,7 +1451,8 @@ where
                     || secondary_nephew!().unwrap().as_mut().is_black())
             {
                 sibling_ptr.as_mut().color = Color::Red;
-                link = node_ptr.as_mut().parent;
+                link = parent;
+                parent = parent_ptr!().as_ref().parent;
                 continue;
             }
 
@@ -532,6 +1490,1900 @@ where
             link = self.root;
         }
 
-        link.unwrap().as_mut().color = Color::Black;
+        if let Some(mut node_ptr) = link {
+            node_ptr.as_mut().color = Color::Black;
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), InvariantViolation> {
+        if let Some(root_ptr) = self.root {
+            unsafe {
+                if root_ptr.as_ref().is_red() {
+                    return Err(InvariantViolation::RedRoot);
+                }
+                if root_ptr.as_ref().parent.is_some() {
+                    return Err(InvariantViolation::BrokenParentLink);
+                }
+            }
+        }
+
+        unsafe { self.validate_subtree(self.root, None, None)? };
+
+        if self.len != Node::size(self.root) {
+            return Err(InvariantViolation::SizeMismatch);
+        }
+
+        Ok(())
+    }
+
+    unsafe fn validate_subtree(
+        &self,
+        link: Link<K, V, A>,
+        lower: Option<&K>,
+        upper: Option<&K>,
+    ) -> Result<usize, InvariantViolation> {
+        let Some(node_ptr) = link else {
+            return Ok(0);
+        };
+        let node = &*node_ptr.as_ptr();
+
+        if lower.is_some_and(|lower| self.comparator.compare(&node.key, lower).is_le())
+            || upper.is_some_and(|upper| self.comparator.compare(&node.key, upper).is_ge())
+        {
+            return Err(InvariantViolation::KeyOrder);
+        }
+
+        for child_ptr in [node.left, node.right].into_iter().flatten() {
+            if child_ptr.as_ref().parent != link {
+                return Err(InvariantViolation::BrokenParentLink);
+            }
+            if node.is_red() && child_ptr.as_ref().is_red() {
+                return Err(InvariantViolation::RedChildOfRed);
+            }
+        }
+
+        let left_height = self.validate_subtree(node.left, lower, Some(&node.key))?;
+        let right_height = self.validate_subtree(node.right, Some(&node.key), upper)?;
+
+        if left_height != right_height {
+            return Err(InvariantViolation::BlackHeightMismatch);
+        }
+        if node.size != Node::size(node.left) + Node::size(node.right) + 1 {
+            return Err(InvariantViolation::SizeMismatch);
+        }
+
+        Ok(left_height + node.is_black() as usize)
+    }
+}
+
+impl<K, V, A, C> RbMap<K, V, A, C> {
+    fn links(&self) -> Links<'_, K, V, A> {
+        // SAFETY: the map owns its nodes and cannot change while borrowed.
+        unsafe { Links::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn iter(&self) -> MapIter<'_, K, V, A> {
+        let (front, back) = match self.root {
+            None => (None, None),
+            Some(root_ptr) => unsafe {
+                (Some(Node::minimum(root_ptr)), Some(Node::maximum(root_ptr)))
+            },
+        };
+
+        MapIter {
+            front,
+            back,
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        unsafe { Self::destroy_subtree(self.root) };
+
+        self.root = None;
+        self.len = 0;
+    }
+
+    unsafe fn clone_subtree(link: Link<K, V, A>, parent: Link<K, V, A>) -> Link<K, V, A>
+    where
+        K: Clone,
+        V: Clone,
+        A: Clone,
+    {
+        let node_ptr = link?;
+        let node = node_ptr.as_ref();
+
+        let mut clone_ptr = NodePtr::from(Box::leak(Box::new(Node {
+            parent,
+            left: None,
+            right: None,
+            color: node.color,
+            size: node.size,
+            augment: node.augment.clone(),
+            key: node.key.clone(),
+            value: node.value.clone(),
+        })));
+        clone_ptr.as_mut().left = Self::clone_subtree(node.left, Some(clone_ptr));
+        clone_ptr.as_mut().right = Self::clone_subtree(node.right, Some(clone_ptr));
+
+        Some(clone_ptr)
+    }
+
+    unsafe fn destroy_subtree(mut link: Link<K, V, A>) {
+        // Rotating left children up flattens the subtree into a right spine
+        // without needing a stack or intact parent links.
+        while let Some(mut node_ptr) = link {
+            match node_ptr.as_ref().left {
+                Some(mut left_ptr) => {
+                    node_ptr.as_mut().left = left_ptr.as_ref().right;
+                    left_ptr.as_mut().right = Some(node_ptr);
+                    link = Some(left_ptr);
+                }
+                None => {
+                    link = node_ptr.as_ref().right;
+                    Node::destroy(node_ptr);
+                }
+            }
+        }
+    }
+}
+
+impl<K, V, A: Augment<K>, C: Comparator<K> + Default> Default for RbMap<K, V, A, C> {
+    fn default() -> Self {
+        Self {
+            root: None,
+            len: 0,
+            comparator: C::default(),
+        }
+    }
+}
+
+// SAFETY: an RbMap owns every node reachable from its root exactly like a Box
+// would, and the raw pointers are never shared with another map. Moving the map
+// to another thread moves the keys and values with it.
+unsafe impl<K: Send, V: Send, A: Send, C: Send> Send for RbMap<K, V, A, C> {}
+
+// SAFETY: every method taking &self only reads the nodes, so sharing the map is
+// the same as sharing &K, &V and the comparator.
+unsafe impl<K: Sync, V: Sync, A: Sync, C: Sync> Sync for RbMap<K, V, A, C> {}
+
+impl<K, V, A, C> Drop for RbMap<K, V, A, C> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<K: Clone, V: Clone, A: Clone, C: Clone> Clone for RbMap<K, V, A, C> {
+    fn clone(&self) -> Self {
+        Self {
+            root: unsafe { Self::clone_subtree(self.root, None) },
+            len: self.len,
+            comparator: self.comparator.clone(),
+        }
+    }
+}
+
+impl<K: PartialEq, V: PartialEq, A, C> PartialEq for RbMap<K, V, A, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Eq, V: Eq, A, C> Eq for RbMap<K, V, A, C> {}
+
+impl<K: PartialOrd, V: PartialOrd, A, C> PartialOrd for RbMap<K, V, A, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K: Ord, V: Ord, A, C> Ord for RbMap<K, V, A, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<K: Hash, V: Hash, A, C> Hash for RbMap<K, V, A, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        for entry in self {
+            entry.hash(state);
+        }
+    }
+}
+
+impl<K: Debug, V: Debug, A, C> Debug for RbMap<K, V, A, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, A, C> FromIterator<(K, V)> for RbMap<K, V, A, C>
+where
+    A: Augment<K>,
+    C: Comparator<K> + Default + Clone,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
     }
 }
+
+impl<K, V, A: Augment<K>, C: Comparator<K> + Clone> Extend<(K, V)> for RbMap<K, V, A, C> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let mut entries: Vec<(K, V)> = iter.into_iter().collect();
+
+        let is_sorted = entries.is_sorted_by(|a, b| self.comparator.compare(&a.0, &b.0).is_le());
+        let is_after = match (self.last(), entries.first()) {
+            (Some((max, _)), Some((first, _))) => self.comparator.compare(max, first).is_lt(),
+            _ => true,
+        };
+
+        if is_sorted && is_after {
+            Self::dedup_sorted(&mut entries, &self.comparator);
+            let comparator = self.comparator.clone();
+            self.append(&mut Self::from_sorted_vec(entries, comparator));
+        } else {
+            for (key, value) in entries {
+                self.insert(key, value);
+            }
+        }
+    }
+}
+
+impl<K> RbTree<K> {
+    pub fn new() -> Self
+    where
+        K: Ord,
+    {
+        Self { map: RbMap::new() }
+    }
+
+    pub fn from_sorted_iter<I>(iter: I) -> Self
+    where
+        K: Ord,
+        I: IntoIterator<Item = K>,
+    {
+        Self {
+            map: RbMap::from_sorted_iter(iter.into_iter().map(|key| (key, ()))),
+        }
+    }
+}
+
+impl<K, C: Comparator<K>> RbTree<K, (), C> {
+    pub fn with_comparator(comparator: C) -> Self {
+        Self {
+            map: RbMap::with_comparator(comparator),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl<K, A: Augment<K>, C: Comparator<K>> RbTree<K, A, C> {
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+    {
+        Range {
+            iter: self.map.range(range),
+        }
+    }
+
+    pub fn range_aggregate<Q, R>(&self, range: R) -> Option<A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+        A: Clone,
+    {
+        self.map.range_aggregate(range)
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn first(&self) -> Option<&K> {
+        self.map.first().map(|(key, _)| key)
+    }
+
+    pub fn last(&self) -> Option<&K> {
+        self.map.last().map(|(key, _)| key)
+    }
+
+    pub fn floor<Q>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.map.floor(key).map(|(key, _)| key)
+    }
+
+    pub fn ceiling<Q>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.map.ceiling(key).map(|(key, _)| key)
+    }
+
+    pub fn predecessor<Q>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.map.predecessor(key).map(|(key, _)| key)
+    }
+
+    pub fn successor<Q>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.map.successor(key).map(|(key, _)| key)
+    }
+
+    pub fn validate(&self) -> Result<(), InvariantViolation> {
+        self.map.validate()
+    }
+
+    pub fn select(&self, index: usize) -> Option<&K> {
+        self.map.select(index).map(|(key, _)| key)
+    }
+
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.map.rank(key)
+    }
+
+    pub fn split_off<Q>(&mut self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q> + Clone,
+    {
+        Self {
+            map: self.map.split_off(key),
+        }
+    }
+
+    // The set iterators walk both trees in order and compare them with
+    // `self`'s comparator, so `other` must be sorted by an equivalent one.
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, Iter<'a, K, A>, C> {
+        self.map.debug_assert_same_order(&other.map);
+
+        Union {
+            iter: self.iter().peekable(),
+            other: other.iter().peekable(),
+            comparator: &self.map.comparator,
+        }
+    }
+
+    // Both trees must share an equivalent comparator, as for union.
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, Iter<'a, K, A>, C> {
+        self.map.debug_assert_same_order(&other.map);
+
+        Intersection {
+            iter: self.iter().peekable(),
+            other: other.iter().peekable(),
+            comparator: &self.map.comparator,
+        }
+    }
+
+    // Both trees must share an equivalent comparator, as for union.
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, Iter<'a, K, A>, C> {
+        self.map.debug_assert_same_order(&other.map);
+
+        Difference {
+            iter: self.iter().peekable(),
+            other: other.iter().peekable(),
+            comparator: &self.map.comparator,
+        }
+    }
+
+    // Items from both trees come out in `self`'s order, which `other` must
+    // share.
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> SymmetricDifference<'a, Iter<'a, K, A>, C> {
+        self.map.debug_assert_same_order(&other.map);
+
+        SymmetricDifference {
+            iter: self.iter().peekable(),
+            other: other.iter().peekable(),
+            comparator: &self.map.comparator,
+        }
+    }
+
+    // `other` must be ordered by a comparator equivalent to `self`'s; see
+    // RbMap::union_with.
+    pub fn union_with(&mut self, other: Self)
+    where
+        C: Clone,
+    {
+        self.map.union_with(other.map);
+    }
+
+    // Same comparator requirement as union_with.
+    pub fn intersect_with(&mut self, other: Self)
+    where
+        C: Clone,
+    {
+        self.map.intersect_with(other.map);
+    }
+
+    // Same comparator requirement as union_with.
+    pub fn difference_with(&mut self, other: Self)
+    where
+        C: Clone,
+    {
+        self.map.difference_with(other.map);
+    }
+
+    // Same comparator requirement as union_with.
+    pub fn symmetric_difference_with(&mut self, other: Self)
+    where
+        C: Clone,
+    {
+        self.map.symmetric_difference_with(other.map);
+    }
+
+    // Same comparator requirement as RbMap::append.
+    pub fn append(&mut self, other: &mut Self)
+    where
+        C: Clone,
+    {
+        self.map.append(&mut other.map);
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn inorder_traverse(&self) -> Vec<&K> {
+        shape::inorder(self.map.links(), self.map.root, self.len())
+    }
+
+    pub fn preorder_tranverse(&self) -> Vec<&K> {
+        shape::preorder(self.map.links(), self.map.root, self.len())
+    }
+
+    pub fn postorder_traverse(&self) -> Vec<&K> {
+        shape::postorder(self.map.links(), self.map.root, self.len())
+    }
+
+    pub fn level_order_traverse(&self) -> Vec<Vec<&K>> {
+        shape::level_order(self.map.links(), self.map.root)
+    }
+
+    pub fn insert(&mut self, key: K) -> bool {
+        self.map.insert(key, ()).is_none()
+    }
+
+    pub fn delete<Q>(&mut self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.map.remove_entry(key).map(|(key, _)| key)
+    }
+
+    pub fn retain<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        self.map.retain(|key, _| pred(key));
+    }
+
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, F, A, C>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let next = self
+            .map
+            .root
+            .map(|root_ptr| unsafe { Node::minimum(root_ptr) });
+
+        ExtractIf {
+            map: &mut self.map,
+            next,
+            pred,
+        }
+    }
+
+    pub fn lower_bound<Q>(&self, bound: Bound<&Q>) -> Cursor<'_, K, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        Cursor {
+            current: self.map.find_lower_bound(bound),
+            root: self.map.root,
+            links: self.map.links(),
+        }
+    }
+
+    pub fn upper_bound<Q>(&self, bound: Bound<&Q>) -> Cursor<'_, K, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        Cursor {
+            current: self.map.find_upper_bound(bound),
+            root: self.map.root,
+            links: self.map.links(),
+        }
+    }
+
+    pub fn lower_bound_mut<Q>(&mut self, bound: Bound<&Q>) -> CursorMut<'_, K, A, C>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        CursorMut {
+            current: self.map.find_lower_bound(bound),
+            map: &mut self.map,
+        }
+    }
+
+    pub fn upper_bound_mut<Q>(&mut self, bound: Bound<&Q>) -> CursorMut<'_, K, A, C>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        CursorMut {
+            current: self.map.find_upper_bound(bound),
+            map: &mut self.map,
+        }
+    }
+}
+
+impl<K, A, C> RbTree<K, A, C> {
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, A> {
+        Iter {
+            iter: self.map.iter(),
+        }
+    }
+
+    pub fn to_dot(&self) -> String
+    where
+        K: Debug,
+    {
+        shape::to_dot(self.map.links(), self.map.root)
+    }
+
+    pub fn to_pretty_string(&self) -> String
+    where
+        K: Debug,
+    {
+        shape::to_pretty_string(self.map.links(), self.map.root)
+    }
+
+    pub fn pretty_print(&self)
+    where
+        K: Debug,
+    {
+        print!("{}", self.to_pretty_string());
+    }
+}
+
+impl<K, A: Augment<K>, C: Comparator<K> + Default> Default for RbTree<K, A, C> {
+    fn default() -> Self {
+        Self {
+            map: RbMap::default(),
+        }
+    }
+}
+
+impl<K: Clone, A: Clone, C: Clone> Clone for RbTree<K, A, C> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<K: PartialEq, A, C> PartialEq for RbTree<K, A, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K: Eq, A, C> Eq for RbTree<K, A, C> {}
+
+impl<K: PartialOrd, A, C> PartialOrd for RbTree<K, A, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.map.partial_cmp(&other.map)
+    }
+}
+
+impl<K: Ord, A, C> Ord for RbTree<K, A, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.map.cmp(&other.map)
+    }
+}
+
+impl<K: Hash, A, C> Hash for RbTree<K, A, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.map.hash(state);
+    }
+}
+
+impl<K: Debug, A, C> Debug for RbTree<K, A, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K, A, C> FromIterator<K> for RbTree<K, A, C>
+where
+    A: Augment<K>,
+    C: Comparator<K> + Default + Clone,
+{
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        Self {
+            map: iter.into_iter().map(|key| (key, ())).collect(),
+        }
+    }
+}
+
+impl<K, A: Augment<K>, C: Comparator<K> + Clone> Extend<K> for RbTree<K, A, C> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        self.map.extend(iter.into_iter().map(|key| (key, ())));
+    }
+}
+
+pub struct ConcurrentRbTree<K> {
+    tree: RwLock<RbTree<K>>,
+}
+
+impl<K> ConcurrentRbTree<K> {
+    pub fn new() -> Self
+    where
+        K: Ord,
+    {
+        Self {
+            tree: RwLock::new(RbTree::new()),
+        }
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, RbTree<K>> {
+        self.tree.read().unwrap()
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, RbTree<K>> {
+        self.tree.write().unwrap()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + Ord,
+        Q: Ord + ?Sized,
+    {
+        self.read().contains(key)
+    }
+
+    pub fn insert(&self, key: K) -> bool
+    where
+        K: Ord,
+    {
+        self.write().insert(key)
+    }
+
+    pub fn delete<Q>(&self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q> + Ord,
+        Q: Ord + ?Sized,
+    {
+        self.write().delete(key)
+    }
+
+    pub fn snapshot(&self) -> RbTree<K>
+    where
+        K: Clone,
+    {
+        self.read().clone()
+    }
+
+    pub fn into_inner(self) -> RbTree<K> {
+        self.tree.into_inner().unwrap()
+    }
+}
+
+impl<K: Ord> Default for ConcurrentRbTree<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> From<RbTree<K>> for ConcurrentRbTree<K> {
+    fn from(tree: RbTree<K>) -> Self {
+        Self {
+            tree: RwLock::new(tree),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RbMultiSet<K> {
+    map: RbMap<K, usize>,
+    len: usize,
+}
+
+impl<K> RbMultiSet<K> {
+    pub fn new() -> Self
+    where
+        K: Ord,
+    {
+        Self {
+            map: RbMap::new(),
+            len: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn distinct_len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn iter(&self) -> MultiIter<'_, K> {
+        MultiIter {
+            iter: self.map.iter(),
+            current: None,
+            len: self.len,
+        }
+    }
+
+    pub fn count<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q> + Ord,
+        Q: Ord + ?Sized,
+    {
+        self.map.get(key).copied().unwrap_or(0)
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + Ord,
+        Q: Ord + ?Sized,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: K) -> usize
+    where
+        K: Ord,
+    {
+        let count = self.map.entry(key).or_insert(0);
+        *count += 1;
+        self.len += 1;
+
+        *count
+    }
+
+    pub fn remove_one<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + Ord,
+        Q: Ord + ?Sized,
+    {
+        match self.map.find_entry(key) {
+            None => return false,
+            Some(mut entry) if *entry.get() > 1 => *entry.get_mut() -= 1,
+            Some(entry) => {
+                entry.remove();
+            }
+        }
+        self.len -= 1;
+
+        true
+    }
+
+    pub fn remove_all<Q>(&mut self, key: &Q) -> usize
+    where
+        K: Borrow<Q> + Ord,
+        Q: Ord + ?Sized,
+    {
+        let count = self.map.remove(key).unwrap_or(0);
+        self.len -= count;
+
+        count
+    }
+
+    pub fn first(&self) -> Option<(&K, usize)>
+    where
+        K: Ord,
+    {
+        self.map.first().map(|(key, count)| (key, *count))
+    }
+
+    pub fn last(&self) -> Option<(&K, usize)>
+    where
+        K: Ord,
+    {
+        self.map.last().map(|(key, count)| (key, *count))
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.len = 0;
+    }
+}
+
+impl<K: Ord> Default for RbMultiSet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Debug> Debug for RbMultiSet<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord> FromIterator<K> for RbMultiSet<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<K: Ord> Extend<K> for RbMultiSet<K> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval<T> {
+    pub start: T,
+    pub end: T,
+}
+
+impl<T: Ord> Interval<T> {
+    pub fn new(start: T, end: T) -> Self {
+        assert!(start <= end, "interval start is greater than interval end");
+
+        Self { start, end }
+    }
+
+    pub fn contains(&self, point: &T) -> bool {
+        &self.start <= point && point <= &self.end
+    }
+
+    pub fn overlaps(&self, start: &T, end: &T) -> bool {
+        &self.start <= end && start <= &self.end
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MaxEnd<T>(T);
+
+impl<T: Ord + Clone> Augment<Interval<T>> for MaxEnd<T> {
+    fn from_key(key: &Interval<T>) -> Self {
+        MaxEnd(key.end.clone())
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        MaxEnd(self.0.clone().max(other.0.clone()))
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct IntervalTree<T> {
+    tree: RbTree<Interval<T>, MaxEnd<T>>,
+}
+
+impl<T: Ord + Clone> IntervalTree<T> {
+    pub fn new() -> Self {
+        Self {
+            tree: RbTree::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn iter(&self) -> Iter<'_, Interval<T>, MaxEnd<T>> {
+        self.tree.iter()
+    }
+
+    pub fn contains(&self, start: T, end: T) -> bool {
+        start <= end && self.tree.contains(&Interval::new(start, end))
+    }
+
+    pub fn insert(&mut self, start: T, end: T) -> bool {
+        start <= end && self.tree.insert(Interval::new(start, end))
+    }
+
+    pub fn remove(&mut self, start: T, end: T) -> bool {
+        start <= end && self.tree.delete(&Interval::new(start, end)).is_some()
+    }
+
+    pub fn max_end(&self) -> Option<&T> {
+        self.tree
+            .map
+            .root
+            .map(|root_ptr| unsafe { &(*root_ptr.as_ptr()).augment.0 })
+    }
+
+    pub fn overlapping(&self, point: T) -> Overlapping<'_, T> {
+        self.overlapping_range(point.clone(), point)
+    }
+
+    pub fn overlapping_range(&self, start: T, end: T) -> Overlapping<'_, T> {
+        let mut overlapping = Overlapping {
+            stack: Vec::new(),
+            start,
+            end,
+            marker: PhantomData,
+        };
+        // A reversed range is empty, so nothing overlaps it.
+        if overlapping.start <= overlapping.end {
+            overlapping.push_left(self.tree.map.root);
+        }
+        overlapping
+    }
+
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+}
+
+impl<T: Ord + Clone> Default for IntervalTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug> Debug for IntervalTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(
+                self.tree
+                    .iter()
+                    .map(|interval| &interval.start..=&interval.end),
+            )
+            .finish()
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<(T, T)> for IntervalTree<T> {
+    fn from_iter<I: IntoIterator<Item = (T, T)>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T: Ord + Clone> Extend<(T, T)> for IntervalTree<T> {
+    fn extend<I: IntoIterator<Item = (T, T)>>(&mut self, iter: I) {
+        self.tree.extend(
+            iter.into_iter()
+                .filter(|(start, end)| start <= end)
+                .map(|(start, end)| Interval::new(start, end)),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    RedRoot,
+    RedChildOfRed,
+    BlackHeightMismatch,
+    BrokenParentLink,
+    KeyOrder,
+    SizeMismatch,
+}
+
+impl Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            InvariantViolation::RedRoot => "root node is red",
+            InvariantViolation::RedChildOfRed => "red node has a red child",
+            InvariantViolation::BlackHeightMismatch => "black heights of subtrees differ",
+            InvariantViolation::BrokenParentLink => "child does not point back to its parent",
+            InvariantViolation::KeyOrder => "keys are not in binary search tree order",
+            InvariantViolation::SizeMismatch => "stored subtree size is wrong",
+        };
+
+        f.write_str(message)
+    }
+}
+
+impl Error for InvariantViolation {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnorderedKeyError;
+
+impl Display for UnorderedKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("key does not fit between its neighbours at the cursor")
+    }
+}
+
+impl Error for UnorderedKeyError {}
+
+#[derive(Clone, Copy)]
+enum SetOp {
+    Union,
+    Intersection,
+    Difference,
+    SymmetricDifference,
+}
+
+struct Split<K, V, A, C> {
+    left: RbMap<K, V, A, C>,
+    left_height: usize,
+    found: Link<K, V, A>,
+    right: RbMap<K, V, A, C>,
+    right_height: usize,
+}
+
+pub enum Entry<'a, K, V, A = (), C = Natural> {
+    Vacant(VacantEntry<'a, K, V, A, C>),
+    Occupied(OccupiedEntry<'a, K, V, A, C>),
+}
+
+pub struct VacantEntry<'a, K, V, A = (), C = Natural> {
+    map: &'a mut RbMap<K, V, A, C>,
+    key: K,
+    parent: Link<K, V, A>,
+    link_ptr: LinkPtr<K, V, A>,
+}
+
+pub struct OccupiedEntry<'a, K, V, A = (), C = Natural> {
+    map: &'a mut RbMap<K, V, A, C>,
+    node_ptr: NodePtr<K, V, A>,
+}
+
+impl<'a, K, V, A: Augment<K>, C: Comparator<K>> Entry<'a, K, V, A, C> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Vacant(entry) => entry.key(),
+            Entry::Occupied(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default()),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+        }
+    }
+}
+
+impl<'a, K, V, A: Augment<K>, C: Comparator<K>> VacantEntry<'a, K, V, A, C> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        unsafe {
+            let node_ptr =
+                self.map
+                    .insert_entry_at_pos(self.parent, self.link_ptr, self.key, value);
+
+            &mut (*node_ptr.as_ptr()).value
+        }
+    }
+}
+
+// SAFETY: entries hold the map's unique borrow, so they are as thread-safe as
+// &mut RbMap<K, V, A, C>.
+unsafe impl<K: Send, V: Send, A: Send, C: Send> Send for VacantEntry<'_, K, V, A, C> {}
+unsafe impl<K: Sync, V: Sync, A: Sync, C: Sync> Sync for VacantEntry<'_, K, V, A, C> {}
+unsafe impl<K: Send, V: Send, A: Send, C: Send> Send for OccupiedEntry<'_, K, V, A, C> {}
+unsafe impl<K: Sync, V: Sync, A: Sync, C: Sync> Sync for OccupiedEntry<'_, K, V, A, C> {}
+
+impl<'a, K, V, A: Augment<K>, C: Comparator<K>> OccupiedEntry<'a, K, V, A, C> {
+    pub fn key(&self) -> &K {
+        unsafe { &(*self.node_ptr.as_ptr()).key }
+    }
+
+    pub fn get(&self) -> &V {
+        unsafe { &(*self.node_ptr.as_ptr()).value }
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { &mut (*self.node_ptr.as_ptr()).value }
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { &mut (*self.node_ptr.as_ptr()).value }
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        unsafe { self.map.remove_entry_at_occupied_pos(self.node_ptr) }
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+pub struct MapIter<'a, K, V, A = ()> {
+    front: Link<K, V, A>,
+    back: Link<K, V, A>,
+    len: usize,
+    marker: PhantomData<&'a Node<K, V, A>>,
+}
+
+impl<'a, K, V, A> Iterator for MapIter<'a, K, V, A> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let node_ptr = self.front?;
+        self.len -= 1;
+
+        unsafe {
+            self.front = Node::successor(node_ptr);
+
+            let node = &*node_ptr.as_ptr();
+            Some((&node.key, &node.value))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V, A> DoubleEndedIterator for MapIter<'_, K, V, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let node_ptr = self.back?;
+        self.len -= 1;
+
+        unsafe {
+            self.back = Node::predecessor(node_ptr);
+
+            let node = &*node_ptr.as_ptr();
+            Some((&node.key, &node.value))
+        }
+    }
+}
+
+impl<K, V, A> ExactSizeIterator for MapIter<'_, K, V, A> {}
+
+impl<K, V, A> FusedIterator for MapIter<'_, K, V, A> {}
+
+// SAFETY: a MapIter behaves like a pair of &K and &V borrowed from the map.
+unsafe impl<K: Sync, V: Sync, A: Sync> Send for MapIter<'_, K, V, A> {}
+unsafe impl<K: Sync, V: Sync, A: Sync> Sync for MapIter<'_, K, V, A> {}
+
+impl<'a, K, V, A, C> IntoIterator for &'a RbMap<K, V, A, C> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = MapIter<'a, K, V, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, K, A = ()> {
+    iter: MapIter<'a, K, (), A>,
+}
+
+impl<'a, K, A> Iterator for Iter<'a, K, A> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, A> DoubleEndedIterator for Iter<'_, K, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(key, _)| key)
+    }
+}
+
+impl<K, A> ExactSizeIterator for Iter<'_, K, A> {}
+
+impl<K, A> FusedIterator for Iter<'_, K, A> {}
+
+impl<'a, K, A, C> IntoIterator for &'a RbTree<K, A, C> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Cursor<'a, K, A = ()> {
+    current: Link<K, (), A>,
+    root: Link<K, (), A>,
+    links: Links<'a, K, (), A>,
+}
+
+impl<K, A> Clone for Cursor<'_, K, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, A> Copy for Cursor<'_, K, A> {}
+
+impl<'a, K, A> Cursor<'a, K, A> {
+    pub fn key(&self) -> Option<&'a K> {
+        self.current.map(|node_ptr| self.links.key(node_ptr))
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = shape::next(self.links, self.root, self.current);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = shape::prev(self.links, self.root, self.current);
+    }
+
+    pub fn peek_next(&self) -> Option<&'a K> {
+        let mut cursor = *self;
+        cursor.move_next();
+        cursor.key()
+    }
+
+    pub fn peek_prev(&self) -> Option<&'a K> {
+        let mut cursor = *self;
+        cursor.move_prev();
+        cursor.key()
+    }
+}
+
+// SAFETY: a Cursor behaves like a &K borrowed from the tree.
+unsafe impl<K: Sync, A: Sync> Send for Cursor<'_, K, A> {}
+unsafe impl<K: Sync, A: Sync> Sync for Cursor<'_, K, A> {}
+
+pub struct CursorMut<'a, K, A = (), C = Natural> {
+    map: &'a mut RbMap<K, (), A, C>,
+    current: Link<K, (), A>,
+}
+
+// SAFETY: a CursorMut holds the tree's unique borrow, so it is as thread-safe
+// as &mut RbMap<K, (), A, C>.
+unsafe impl<K: Send, A: Send, C: Send> Send for CursorMut<'_, K, A, C> {}
+unsafe impl<K: Sync, A: Sync, C: Sync> Sync for CursorMut<'_, K, A, C> {}
+
+impl<K, A, C> CursorMut<'_, K, A, C> {
+    pub fn key(&self) -> Option<&K> {
+        self.as_cursor().key()
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = shape::next(self.map.links(), self.map.root, self.current);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = shape::prev(self.map.links(), self.map.root, self.current);
+    }
+
+    pub fn peek_next(&self) -> Option<&K> {
+        self.as_cursor().peek_next()
+    }
+
+    pub fn peek_prev(&self) -> Option<&K> {
+        self.as_cursor().peek_prev()
+    }
+
+    pub fn as_cursor(&self) -> Cursor<'_, K, A> {
+        Cursor {
+            current: self.current,
+            root: self.map.root,
+            links: self.map.links(),
+        }
+    }
+}
+
+impl<K, A: Augment<K>, C: Comparator<K>> CursorMut<'_, K, A, C> {
+    pub fn remove_current(&mut self) -> Option<K> {
+        let node_ptr = self.current?;
+
+        unsafe {
+            self.current = Node::successor(node_ptr);
+            Some(self.map.remove_entry_at_occupied_pos(node_ptr).0)
+        }
+    }
+
+    // Fails without touching the tree unless `key` sorts strictly between
+    // the current key and the next one.
+    pub fn insert_after(&mut self, key: K) -> Result<(), UnorderedKeyError> {
+        let comparator = &self.map.comparator;
+        if self
+            .key()
+            .is_some_and(|current| !comparator.compare(current, &key).is_lt())
+            || self
+                .peek_next()
+                .is_some_and(|next| !comparator.compare(&key, next).is_lt())
+        {
+            return Err(UnorderedKeyError);
+        }
+
+        unsafe {
+            let (parent, link_ptr) = match self.current {
+                Some(mut node_ptr) => match node_ptr.as_ref().right {
+                    None => (
+                        Some(node_ptr),
+                        LinkPtr::new_unchecked(&mut node_ptr.as_mut().right),
+                    ),
+                    Some(right_ptr) => {
+                        let mut min_ptr = Node::minimum(right_ptr);
+                        (
+                            Some(min_ptr),
+                            LinkPtr::new_unchecked(&mut min_ptr.as_mut().left),
+                        )
+                    }
+                },
+                None => self.front_pos(),
+            };
+
+            self.map.insert_entry_at_pos(parent, link_ptr, key, ());
+        }
+
+        Ok(())
+    }
+
+    // Fails without touching the tree unless `key` sorts strictly between
+    // the previous key and the current one.
+    pub fn insert_before(&mut self, key: K) -> Result<(), UnorderedKeyError> {
+        let comparator = &self.map.comparator;
+        if self
+            .key()
+            .is_some_and(|current| !comparator.compare(&key, current).is_lt())
+            || self
+                .peek_prev()
+                .is_some_and(|prev| !comparator.compare(prev, &key).is_lt())
+        {
+            return Err(UnorderedKeyError);
+        }
+
+        unsafe {
+            let (parent, link_ptr) = match self.current {
+                Some(mut node_ptr) => match node_ptr.as_ref().left {
+                    None => (
+                        Some(node_ptr),
+                        LinkPtr::new_unchecked(&mut node_ptr.as_mut().left),
+                    ),
+                    Some(left_ptr) => {
+                        let mut max_ptr = Node::maximum(left_ptr);
+                        (
+                            Some(max_ptr),
+                            LinkPtr::new_unchecked(&mut max_ptr.as_mut().right),
+                        )
+                    }
+                },
+                None => self.back_pos(),
+            };
+
+            self.map.insert_entry_at_pos(parent, link_ptr, key, ());
+        }
+
+        Ok(())
+    }
+
+    unsafe fn front_pos(&mut self) -> (Link<K, (), A>, LinkPtr<K, (), A>) {
+        match self.map.root {
+            None => (None, LinkPtr::new_unchecked(&mut self.map.root)),
+            Some(root_ptr) => {
+                let mut min_ptr = Node::minimum(root_ptr);
+                (
+                    Some(min_ptr),
+                    LinkPtr::new_unchecked(&mut min_ptr.as_mut().left),
+                )
+            }
+        }
+    }
+
+    unsafe fn back_pos(&mut self) -> (Link<K, (), A>, LinkPtr<K, (), A>) {
+        match self.map.root {
+            None => (None, LinkPtr::new_unchecked(&mut self.map.root)),
+            Some(root_ptr) => {
+                let mut max_ptr = Node::maximum(root_ptr);
+                (
+                    Some(max_ptr),
+                    LinkPtr::new_unchecked(&mut max_ptr.as_mut().right),
+                )
+            }
+        }
+    }
+}
+
+pub struct MapExtractIf<'a, K, V, F, A = (), C = Natural> {
+    map: &'a mut RbMap<K, V, A, C>,
+    next: Link<K, V, A>,
+    pred: F,
+}
+
+impl<K, V, F, A, C> Iterator for MapExtractIf<'_, K, V, F, A, C>
+where
+    F: FnMut(&K, &mut V) -> bool,
+    A: Augment<K>,
+    C: Comparator<K>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe { self.map.extract_next(&mut self.next, &mut self.pred) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.map.len))
+    }
+}
+
+impl<K, V, F, A, C> FusedIterator for MapExtractIf<'_, K, V, F, A, C>
+where
+    F: FnMut(&K, &mut V) -> bool,
+    A: Augment<K>,
+    C: Comparator<K>,
+{
+}
+
+// SAFETY: a MapExtractIf holds the map's unique borrow next to its predicate.
+unsafe impl<K: Send, V: Send, F: Send, A: Send, C: Send> Send for MapExtractIf<'_, K, V, F, A, C> {}
+unsafe impl<K: Sync, V: Sync, F: Sync, A: Sync, C: Sync> Sync for MapExtractIf<'_, K, V, F, A, C> {}
+
+pub struct ExtractIf<'a, K, F, A = (), C = Natural> {
+    map: &'a mut RbMap<K, (), A, C>,
+    next: Link<K, (), A>,
+    pred: F,
+}
+
+impl<K, F, A, C> Iterator for ExtractIf<'_, K, F, A, C>
+where
+    F: FnMut(&K) -> bool,
+    A: Augment<K>,
+    C: Comparator<K>,
+{
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pred = &mut self.pred;
+
+        unsafe {
+            self.map
+                .extract_next(&mut self.next, |key, _| pred(key))
+                .map(|(key, _)| key)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.map.len))
+    }
+}
+
+impl<K, F, A, C> FusedIterator for ExtractIf<'_, K, F, A, C>
+where
+    F: FnMut(&K) -> bool,
+    A: Augment<K>,
+    C: Comparator<K>,
+{
+}
+
+// SAFETY: an ExtractIf holds the tree's unique borrow next to its predicate.
+unsafe impl<K: Send, F: Send, A: Send, C: Send> Send for ExtractIf<'_, K, F, A, C> {}
+unsafe impl<K: Sync, F: Sync, A: Sync, C: Sync> Sync for ExtractIf<'_, K, F, A, C> {}
+
+pub struct MapRange<'a, K, V, A = ()> {
+    front: Link<K, V, A>,
+    back: Link<K, V, A>,
+    marker: PhantomData<&'a Node<K, V, A>>,
+}
+
+impl<'a, K, V, A> Iterator for MapRange<'a, K, V, A> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node_ptr = self.front?;
+
+        unsafe {
+            if self.front == self.back {
+                self.front = None;
+                self.back = None;
+            } else {
+                self.front = Node::successor(node_ptr);
+            }
+
+            let node = &*node_ptr.as_ptr();
+            Some((&node.key, &node.value))
+        }
+    }
+}
+
+impl<K, V, A> DoubleEndedIterator for MapRange<'_, K, V, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node_ptr = self.back?;
+
+        unsafe {
+            if self.front == self.back {
+                self.front = None;
+                self.back = None;
+            } else {
+                self.back = Node::predecessor(node_ptr);
+            }
+
+            let node = &*node_ptr.as_ptr();
+            Some((&node.key, &node.value))
+        }
+    }
+}
+
+impl<K, V, A> FusedIterator for MapRange<'_, K, V, A> {}
+
+// SAFETY: a MapRange behaves like a pair of &K and &V borrowed from the map.
+unsafe impl<K: Sync, V: Sync, A: Sync> Send for MapRange<'_, K, V, A> {}
+unsafe impl<K: Sync, V: Sync, A: Sync> Sync for MapRange<'_, K, V, A> {}
+
+pub struct Range<'a, K, A = ()> {
+    iter: MapRange<'a, K, (), A>,
+}
+
+impl<'a, K, A> Iterator for Range<'a, K, A> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, _)| key)
+    }
+}
+
+impl<K, A> DoubleEndedIterator for Range<'_, K, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(key, _)| key)
+    }
+}
+
+impl<K, A> FusedIterator for Range<'_, K, A> {}
+
+// The set iterators merge two sorted iterators, so the pointer and arena
+// trees share them. Both sides must be sorted by `comparator`.
+pub struct Union<'a, I: Iterator, C = Natural> {
+    iter: Peekable<I>,
+    other: Peekable<I>,
+    comparator: &'a C,
+}
+
+impl<'a, K: 'a, I, C> Iterator for Union<'a, I, C>
+where
+    I: Iterator<Item = &'a K>,
+    C: Comparator<K>,
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match (self.iter.peek(), self.other.peek()) {
+            (None, None) => None,
+            (Some(_), None) => self.iter.next(),
+            (None, Some(_)) => self.other.next(),
+            (Some(key), Some(other_key)) => match self.comparator.compare(key, other_key) {
+                Ordering::Less => self.iter.next(),
+                Ordering::Greater => self.other.next(),
+                Ordering::Equal => {
+                    self.other.next();
+                    self.iter.next()
+                }
+            },
+        }
+    }
+}
+
+impl<'a, K: 'a, I, C> FusedIterator for Union<'a, I, C>
+where
+    I: FusedIterator<Item = &'a K>,
+    C: Comparator<K>,
+{
+}
+
+pub struct Intersection<'a, I: Iterator, C = Natural> {
+    iter: Peekable<I>,
+    other: Peekable<I>,
+    comparator: &'a C,
+}
+
+impl<'a, K: 'a, I, C> Iterator for Intersection<'a, I, C>
+where
+    I: Iterator<Item = &'a K>,
+    C: Comparator<K>,
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let key = self.iter.peek()?;
+            let other_key = self.other.peek()?;
+
+            match self.comparator.compare(key, other_key) {
+                Ordering::Less => {
+                    self.iter.next();
+                }
+                Ordering::Greater => {
+                    self.other.next();
+                }
+                Ordering::Equal => {
+                    self.other.next();
+                    return self.iter.next();
+                }
+            }
+        }
+    }
+}
+
+impl<'a, K: 'a, I, C> FusedIterator for Intersection<'a, I, C>
+where
+    I: FusedIterator<Item = &'a K>,
+    C: Comparator<K>,
+{
+}
+
+pub struct Difference<'a, I: Iterator, C = Natural> {
+    iter: Peekable<I>,
+    other: Peekable<I>,
+    comparator: &'a C,
+}
+
+impl<'a, K: 'a, I, C> Iterator for Difference<'a, I, C>
+where
+    I: Iterator<Item = &'a K>,
+    C: Comparator<K>,
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let key = self.iter.peek()?;
+            let Some(other_key) = self.other.peek() else {
+                return self.iter.next();
+            };
+
+            match self.comparator.compare(key, other_key) {
+                Ordering::Less => return self.iter.next(),
+                Ordering::Greater => {
+                    self.other.next();
+                }
+                Ordering::Equal => {
+                    self.iter.next();
+                    self.other.next();
+                }
+            }
+        }
+    }
+}
+
+impl<'a, K: 'a, I, C> FusedIterator for Difference<'a, I, C>
+where
+    I: FusedIterator<Item = &'a K>,
+    C: Comparator<K>,
+{
+}
+
+pub struct SymmetricDifference<'a, I: Iterator, C = Natural> {
+    iter: Peekable<I>,
+    other: Peekable<I>,
+    comparator: &'a C,
+}
+
+impl<'a, K: 'a, I, C> Iterator for SymmetricDifference<'a, I, C>
+where
+    I: Iterator<Item = &'a K>,
+    C: Comparator<K>,
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match (self.iter.peek(), self.other.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => return self.iter.next(),
+                (None, Some(_)) => return self.other.next(),
+                (Some(key), Some(other_key)) => match self.comparator.compare(key, other_key) {
+                    Ordering::Less => return self.iter.next(),
+                    Ordering::Greater => return self.other.next(),
+                    Ordering::Equal => {
+                        self.iter.next();
+                        self.other.next();
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<'a, K: 'a, I, C> FusedIterator for SymmetricDifference<'a, I, C>
+where
+    I: FusedIterator<Item = &'a K>,
+    C: Comparator<K>,
+{
+}
+
+pub struct MultiIter<'a, K> {
+    iter: MapIter<'a, K, usize>,
+    current: Option<(&'a K, usize)>,
+    len: usize,
+}
+
+impl<'a, K> Iterator for MultiIter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, remaining) = match self.current {
+            Some((key, remaining)) if remaining > 0 => (key, remaining),
+            _ => {
+                let (key, count) = self.iter.next()?;
+                (key, *count)
+            }
+        };
+
+        self.current = Some((key, remaining - 1));
+        self.len -= 1;
+
+        Some(key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K> ExactSizeIterator for MultiIter<'_, K> {}
+
+impl<K> FusedIterator for MultiIter<'_, K> {}
+
+impl<'a, K> IntoIterator for &'a RbMultiSet<K> {
+    type Item = &'a K;
+    type IntoIter = MultiIter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Overlapping<'a, T> {
+    stack: Vec<NodePtr<Interval<T>, (), MaxEnd<T>>>,
+    start: T,
+    end: T,
+    marker: PhantomData<&'a Interval<T>>,
+}
+
+impl<T: Ord> Overlapping<'_, T> {
+    fn push_left(&mut self, mut link: Link<Interval<T>, (), MaxEnd<T>>) {
+        while let Some(node_ptr) = link {
+            unsafe {
+                if node_ptr.as_ref().augment.0 < self.start {
+                    return;
+                }
+                self.stack.push(node_ptr);
+                link = node_ptr.as_ref().left;
+            }
+        }
+    }
+}
+
+impl<'a, T: Ord> Iterator for Overlapping<'a, T> {
+    type Item = &'a Interval<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node_ptr) = self.stack.pop() {
+            unsafe {
+                let interval = &(*node_ptr.as_ptr()).key;
+                if interval.start > self.end {
+                    self.stack.clear();
+                    return None;
+                }
+
+                self.push_left(node_ptr.as_ref().right);
+
+                if interval.end >= self.start {
+                    return Some(interval);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<T: Ord> FusedIterator for Overlapping<'_, T> {}
+
+// SAFETY: an Overlapping behaves like a stack of &Interval<T> borrowed from the
+// tree next to the owned query bounds.
+unsafe impl<T: Send + Sync> Send for Overlapping<'_, T> {}
+unsafe impl<T: Sync> Sync for Overlapping<'_, T> {}
@@ -0,0 +1,1431 @@
+//! An RbTree whose nodes live in a single `Vec` and link to each other by
+//! `u32` index. It mirrors `RbTree<K>` with the default natural order and no
+//! augmentation, so `with_comparator` and `range_aggregate` have no
+//! counterpart here. There is no index-based join or split, so `split_off`,
+//! `retain`, `append` and the in-place set operations rebuild the arena from
+//! sorted keys in O(n + m). The set operations fall back to per-key updates
+//! when one side is small enough for that to be cheaper.
+
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt::{self, Debug},
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
+    ops::{Bound, RangeBounds},
+};
+
+use super::{
+    shape::{self, Shape},
+    Difference, Intersection, InvariantViolation, Natural, SetOp, SymmetricDifference, Union,
+    UnorderedKeyError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Color {
+    Black,
+    Red,
+}
+
+type Index = u32;
+type Link = Option<Index>;
+
+#[derive(Clone, Debug)]
+struct Node<K> {
+    parent: Link,
+    left: Link,
+    right: Link,
+    color: Color,
+    size: usize,
+    key: K,
+}
+
+impl<K> Node<K> {
+    fn is_red(&self) -> bool {
+        self.color == Color::Red
+    }
+
+    fn is_black(&self) -> bool {
+        !self.is_red()
+    }
+}
+
+#[derive(Clone)]
+pub struct ArenaRbTree<K> {
+    nodes: Vec<Node<K>>,
+    root: Link,
+}
+
+impl<K> ArenaRbTree<K> {
+    pub fn new() -> Self
+    where
+        K: Ord,
+    {
+        Self {
+            nodes: Vec::new(),
+            root: None,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self
+    where
+        K: Ord,
+    {
+        Self {
+            nodes: Vec::with_capacity(capacity),
+            root: None,
+        }
+    }
+
+    pub fn from_sorted_iter<I>(iter: I) -> Self
+    where
+        K: Ord,
+        I: IntoIterator<Item = K>,
+    {
+        let mut keys: Vec<K> = iter.into_iter().collect();
+
+        assert!(
+            keys.is_sorted(),
+            "from_sorted_iter requires keys in ascending order"
+        );
+
+        keys.dedup();
+        Self::from_sorted_vec(keys)
+    }
+
+    fn from_sorted_vec(keys: Vec<K>) -> Self {
+        let len = keys.len();
+        Index::try_from(len).expect("arena index overflow");
+
+        let mut nodes: Vec<Node<K>> = keys
+            .into_iter()
+            .map(|key| Node {
+                parent: None,
+                left: None,
+                right: None,
+                color: Color::Black,
+                size: 1,
+                key,
+            })
+            .collect();
+
+        let red_depth = if len == 0 { 0 } else { len.ilog2() as usize };
+        let root = Self::build_sorted(&mut nodes, 0, len, None, 0, red_depth);
+
+        Self { nodes, root }
+    }
+
+    // Slot i holds the i-th smallest key, so each subtree is a contiguous run
+    // of the arena rooted at its midpoint.
+    fn build_sorted(
+        nodes: &mut [Node<K>],
+        start: usize,
+        len: usize,
+        parent: Link,
+        depth: usize,
+        red_depth: usize,
+    ) -> Link {
+        if len == 0 {
+            return None;
+        }
+
+        let left_len = len / 2;
+        let index = start + left_len;
+        let link = Some(index as Index);
+
+        let left = Self::build_sorted(nodes, start, left_len, link, depth + 1, red_depth);
+        let right = Self::build_sorted(
+            nodes,
+            index + 1,
+            len - left_len - 1,
+            link,
+            depth + 1,
+            red_depth,
+        );
+
+        let node = &mut nodes[index];
+        node.parent = parent;
+        node.left = left;
+        node.right = right;
+        node.size = len;
+        node.color = if depth == red_depth && depth > 0 {
+            Color::Red
+        } else {
+            Color::Black
+        };
+
+        link
+    }
+
+    fn into_sorted_vec(self) -> Vec<K> {
+        let mut order: Vec<Index> = Vec::with_capacity(self.len());
+        let mut current = self.root.map(|root| self.minimum(root));
+        while let Some(index) = current {
+            order.push(index);
+            current = self.next_index(index);
+        }
+
+        let mut keys: Vec<Option<K>> = self.nodes.into_iter().map(|node| Some(node.key)).collect();
+        order
+            .into_iter()
+            .map(|index| keys[index as usize].take().unwrap())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter {
+            tree: self,
+            front: self.root.map(|root| self.minimum(root)),
+            back: self.root.map(|root| self.maximum(root)),
+            len: self.len(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.root = None;
+    }
+
+    fn node(&self, index: Index) -> &Node<K> {
+        &self.nodes[index as usize]
+    }
+
+    fn node_mut(&mut self, index: Index) -> &mut Node<K> {
+        &mut self.nodes[index as usize]
+    }
+
+    fn size(&self, link: Link) -> usize {
+        link.map_or(0, |index| self.node(index).size)
+    }
+
+    fn is_red(&self, link: Link) -> bool {
+        link.is_some_and(|index| self.node(index).is_red())
+    }
+
+    fn update(&mut self, index: Index) {
+        let node = self.node(index);
+        let size = self.size(node.left) + self.size(node.right) + 1;
+        self.node_mut(index).size = size;
+    }
+
+    fn update_upwards(&mut self, mut link: Link) {
+        while let Some(index) = link {
+            self.update(index);
+            link = self.node(index).parent;
+        }
+    }
+
+    fn minimum(&self, index: Index) -> Index {
+        shape::minimum(self, index)
+    }
+
+    fn maximum(&self, index: Index) -> Index {
+        shape::maximum(self, index)
+    }
+
+    fn next_index(&self, index: Index) -> Link {
+        shape::successor(self, index)
+    }
+
+    fn prev_index(&self, index: Index) -> Link {
+        shape::predecessor(self, index)
+    }
+
+    fn next_link(&self, link: Link) -> Link {
+        shape::next(self, self.root, link)
+    }
+
+    fn prev_link(&self, link: Link) -> Link {
+        shape::prev(self, self.root, link)
+    }
+
+    fn key(&self, link: Link) -> Option<&K> {
+        link.map(|index| &self.node(index).key)
+    }
+
+    pub fn first(&self) -> Option<&K> {
+        self.key(self.root.map(|root| self.minimum(root)))
+    }
+
+    pub fn last(&self) -> Option<&K> {
+        self.key(self.root.map(|root| self.maximum(root)))
+    }
+
+    pub fn select(&self, index: usize) -> Option<&K> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut index = index;
+        let mut current = self.root;
+        while let Some(node_index) = current {
+            let node = self.node(node_index);
+            let left_size = self.size(node.left);
+
+            match index.cmp(&left_size) {
+                Ordering::Less => current = node.left,
+                Ordering::Equal => return Some(&node.key),
+                Ordering::Greater => {
+                    index -= left_size + 1;
+                    current = node.right;
+                }
+            }
+        }
+        None
+    }
+
+    pub fn inorder_traverse(&self) -> Vec<&K> {
+        shape::inorder(self, self.root, self.len())
+    }
+
+    pub fn preorder_tranverse(&self) -> Vec<&K> {
+        shape::preorder(self, self.root, self.len())
+    }
+
+    pub fn postorder_traverse(&self) -> Vec<&K> {
+        shape::postorder(self, self.root, self.len())
+    }
+
+    pub fn level_order_traverse(&self) -> Vec<Vec<&K>> {
+        shape::level_order(self, self.root)
+    }
+
+    pub fn to_dot(&self) -> String
+    where
+        K: Debug,
+    {
+        shape::to_dot(self, self.root)
+    }
+
+    pub fn to_pretty_string(&self) -> String
+    where
+        K: Debug,
+    {
+        shape::to_pretty_string(self, self.root)
+    }
+
+    pub fn pretty_print(&self)
+    where
+        K: Debug,
+    {
+        print!("{}", self.to_pretty_string());
+    }
+
+    fn replace_child(&mut self, parent: Link, child: Index, replacement: Link) {
+        match parent {
+            None => self.root = replacement,
+            Some(parent) => {
+                let parent = self.node_mut(parent);
+                if parent.left == Some(child) {
+                    parent.left = replacement;
+                } else {
+                    parent.right = replacement;
+                }
+            }
+        }
+    }
+
+    fn transplant(&mut self, index: Index, replacement: Link) {
+        let parent = self.node(index).parent;
+        self.replace_child(parent, index, replacement);
+
+        if let Some(replacement) = replacement {
+            self.node_mut(replacement).parent = parent;
+        }
+    }
+
+    fn rotate_left(&mut self, index: Index) {
+        let Some(right) = self.node(index).right else {
+            return;
+        };
+
+        let right_left = self.node(right).left;
+        self.node_mut(index).right = right_left;
+        if let Some(right_left) = right_left {
+            self.node_mut(right_left).parent = Some(index);
+        }
+
+        self.transplant(index, Some(right));
+
+        self.node_mut(right).left = Some(index);
+        self.node_mut(index).parent = Some(right);
+
+        self.update(index);
+        self.update(right);
+    }
+
+    fn rotate_right(&mut self, index: Index) {
+        let Some(left) = self.node(index).left else {
+            return;
+        };
+
+        let left_right = self.node(left).right;
+        self.node_mut(index).left = left_right;
+        if let Some(left_right) = left_right {
+            self.node_mut(left_right).parent = Some(index);
+        }
+
+        self.transplant(index, Some(left));
+
+        self.node_mut(left).right = Some(index);
+        self.node_mut(index).parent = Some(left);
+
+        self.update(index);
+        self.update(left);
+    }
+
+    fn balance_insert(&mut self, mut index: Index) {
+        while let Some(mut parent) = self.node(index).parent {
+            if self.node(parent).is_black() {
+                break;
+            }
+
+            let Some(grandparent) = self.node(parent).parent else {
+                break;
+            };
+
+            let is_left = self.node(grandparent).left == Some(parent);
+            let uncle = if is_left {
+                self.node(grandparent).right
+            } else {
+                self.node(grandparent).left
+            };
+
+            if let Some(uncle) = uncle.filter(|&uncle| self.node(uncle).is_red()) {
+                self.node_mut(parent).color = Color::Black;
+                self.node_mut(uncle).color = Color::Black;
+                self.node_mut(grandparent).color = Color::Red;
+                index = grandparent;
+                continue;
+            }
+
+            if is_left {
+                if self.node(parent).right == Some(index) {
+                    index = parent;
+                    self.rotate_left(index);
+                    parent = self.node(index).parent.unwrap();
+                }
+                self.node_mut(parent).color = Color::Black;
+                self.node_mut(grandparent).color = Color::Red;
+                self.rotate_right(grandparent);
+            } else {
+                if self.node(parent).left == Some(index) {
+                    index = parent;
+                    self.rotate_right(index);
+                    parent = self.node(index).parent.unwrap();
+                }
+                self.node_mut(parent).color = Color::Black;
+                self.node_mut(grandparent).color = Color::Red;
+                self.rotate_left(grandparent);
+            }
+        }
+
+        if let Some(root) = self.root {
+            self.node_mut(root).color = Color::Black;
+        }
+    }
+
+    fn attach(&mut self, parent: Link, is_left: bool, key: K) -> Index {
+        let index = Index::try_from(self.nodes.len()).expect("arena index overflow");
+        self.nodes.push(Node {
+            parent,
+            left: None,
+            right: None,
+            color: Color::Red,
+            size: 1,
+            key,
+        });
+
+        match parent {
+            None => self.root = Some(index),
+            Some(parent) if is_left => self.node_mut(parent).left = Some(index),
+            Some(parent) => self.node_mut(parent).right = Some(index),
+        }
+
+        self.update_upwards(parent);
+        self.balance_insert(index);
+
+        index
+    }
+
+    // Releasing a slot moves the last node into it, so a caller holding on to
+    // another index (an iterator or cursor position) has it patched here.
+    fn remove_tracking(&mut self, index: Index, tracked: &mut Link) -> K {
+        let last = (self.nodes.len() - 1) as Index;
+        let key = self.remove_at(index);
+
+        if *tracked == Some(last) {
+            *tracked = Some(index);
+        }
+
+        key
+    }
+
+    fn remove_at(&mut self, index: Index) -> K {
+        let mut is_removed_black = self.node(index).is_black();
+        let replacement: Link;
+        let replacement_parent: Link;
+
+        match (self.node(index).left, self.node(index).right) {
+            (None, right) => {
+                replacement = right;
+                replacement_parent = self.node(index).parent;
+                self.transplant(index, right);
+            }
+            (left, None) => {
+                replacement = left;
+                replacement_parent = self.node(index).parent;
+                self.transplant(index, left);
+            }
+            (Some(left), Some(right)) => {
+                let min_child = self.minimum(right);
+                is_removed_black = self.node(min_child).is_black();
+                replacement = self.node(min_child).right;
+
+                if min_child != right {
+                    replacement_parent = self.node(min_child).parent;
+                    self.transplant(min_child, replacement);
+
+                    self.node_mut(min_child).right = Some(right);
+                    self.node_mut(right).parent = Some(min_child);
+                } else {
+                    replacement_parent = Some(min_child);
+                }
+
+                self.transplant(index, Some(min_child));
+                self.node_mut(min_child).left = Some(left);
+                self.node_mut(left).parent = Some(min_child);
+                self.node_mut(min_child).color = self.node(index).color;
+            }
+        }
+
+        self.update_upwards(replacement_parent);
+
+        if is_removed_black {
+            self.balance_delete(replacement, replacement_parent);
+        }
+
+        self.release(index)
+    }
+
+    fn release(&mut self, index: Index) -> K {
+        let last = (self.nodes.len() - 1) as Index;
+        let node = self.nodes.swap_remove(index as usize);
+
+        if index != last {
+            let moved = self.node(index);
+            let (parent, left, right) = (moved.parent, moved.left, moved.right);
+
+            self.replace_child(parent, last, Some(index));
+            for child in [left, right].into_iter().flatten() {
+                self.node_mut(child).parent = Some(index);
+            }
+        }
+
+        node.key
+    }
+
+    fn sibling(&self, parent: Index, is_left_child: bool) -> Index {
+        let parent = self.node(parent);
+        if is_left_child {
+            parent.right.unwrap()
+        } else {
+            parent.left.unwrap()
+        }
+    }
+
+    fn nephews(&self, sibling: Index, is_left_child: bool) -> (Link, Link) {
+        let sibling = self.node(sibling);
+        if is_left_child {
+            (sibling.left, sibling.right)
+        } else {
+            (sibling.right, sibling.left)
+        }
+    }
+
+    fn balance_delete(&mut self, mut link: Link, mut parent: Link) {
+        while link != self.root && !self.is_red(link) {
+            let parent_index = parent.unwrap();
+            let is_left_child = link == self.node(parent_index).left;
+            let mut sibling = self.sibling(parent_index, is_left_child);
+
+            if self.node(sibling).is_red() {
+                self.node_mut(sibling).color = Color::Black;
+                self.node_mut(parent_index).color = Color::Red;
+
+                if is_left_child {
+                    self.rotate_left(parent_index);
+                } else {
+                    self.rotate_right(parent_index);
+                }
+
+                sibling = self.sibling(parent_index, is_left_child);
+            }
+
+            let (primary_nephew, secondary_nephew) = self.nephews(sibling, is_left_child);
+            if !self.is_red(primary_nephew) && !self.is_red(secondary_nephew) {
+                self.node_mut(sibling).color = Color::Red;
+                link = parent;
+                parent = self.node(parent_index).parent;
+                continue;
+            }
+
+            if !self.is_red(secondary_nephew) {
+                if let Some(primary_nephew) = primary_nephew {
+                    self.node_mut(primary_nephew).color = Color::Black;
+                }
+                self.node_mut(sibling).color = Color::Red;
+
+                if is_left_child {
+                    self.rotate_right(sibling);
+                } else {
+                    self.rotate_left(sibling);
+                }
+
+                sibling = self.sibling(parent_index, is_left_child);
+            }
+
+            self.node_mut(sibling).color = self.node(parent_index).color;
+            self.node_mut(parent_index).color = Color::Black;
+            if let (_, Some(secondary_nephew)) = self.nephews(sibling, is_left_child) {
+                self.node_mut(secondary_nephew).color = Color::Black;
+            }
+
+            if is_left_child {
+                self.rotate_left(parent_index);
+            } else {
+                self.rotate_right(parent_index);
+            }
+
+            link = self.root;
+        }
+
+        if let Some(index) = link {
+            self.node_mut(index).color = Color::Black;
+        }
+    }
+}
+
+impl<K: Ord> ArenaRbTree<K> {
+    fn find<Q>(&self, key: &Q) -> Link
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut current = self.root;
+        while let Some(index) = current {
+            let node = self.node(index);
+            current = match key.cmp(node.key.borrow()) {
+                Ordering::Equal => return current,
+                Ordering::Less => node.left,
+                Ordering::Greater => node.right,
+            }
+        }
+        None
+    }
+
+    fn find_lower_bound<Q>(&self, bound: Bound<&Q>) -> Link
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut result = None;
+        let mut current = self.root;
+        while let Some(index) = current {
+            let node = self.node(index);
+            let is_above = match bound {
+                Bound::Included(key) => node.key.borrow() >= key,
+                Bound::Excluded(key) => node.key.borrow() > key,
+                Bound::Unbounded => true,
+            };
+
+            if is_above {
+                result = current;
+                current = node.left;
+            } else {
+                current = node.right;
+            }
+        }
+        result
+    }
+
+    fn find_upper_bound<Q>(&self, bound: Bound<&Q>) -> Link
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut result = None;
+        let mut current = self.root;
+        while let Some(index) = current {
+            let node = self.node(index);
+            let is_below = match bound {
+                Bound::Included(key) => node.key.borrow() <= key,
+                Bound::Excluded(key) => node.key.borrow() < key,
+                Bound::Unbounded => true,
+            };
+
+            if is_below {
+                result = current;
+                current = node.right;
+            } else {
+                current = node.left;
+            }
+        }
+        result
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn floor<Q>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.key(self.find_upper_bound(Bound::Included(key)))
+    }
+
+    pub fn ceiling<Q>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.key(self.find_lower_bound(Bound::Included(key)))
+    }
+
+    pub fn predecessor<Q>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.key(self.find_upper_bound(Bound::Excluded(key)))
+    }
+
+    pub fn successor<Q>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.key(self.find_lower_bound(Bound::Excluded(key)))
+    }
+
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut rank = 0;
+        let mut current = self.root;
+        while let Some(index) = current {
+            let node = self.node(index);
+            if key <= node.key.borrow() {
+                current = node.left;
+            } else {
+                rank += self.size(node.left) + 1;
+                current = node.right;
+            }
+        }
+        rank
+    }
+
+    pub fn insert(&mut self, key: K) -> bool {
+        let mut parent: Link = None;
+        let mut is_left = false;
+        let mut current = self.root;
+        while let Some(index) = current {
+            let node = self.node(index);
+            match key.cmp(&node.key) {
+                Ordering::Equal => return false,
+                Ordering::Less => {
+                    is_left = true;
+                    current = node.left;
+                }
+                Ordering::Greater => {
+                    is_left = false;
+                    current = node.right;
+                }
+            }
+            parent = Some(index);
+        }
+
+        self.attach(parent, is_left, key);
+
+        true
+    }
+
+    pub fn delete<Q>(&mut self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self.find(key)?;
+
+        Some(self.remove_at(index))
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        match (range.start_bound(), range.end_bound()) {
+            (Bound::Excluded(start), Bound::Excluded(end)) if start == end => {
+                panic!("range start and end are equal and excluded")
+            }
+            (
+                Bound::Included(start) | Bound::Excluded(start),
+                Bound::Included(end) | Bound::Excluded(end),
+            ) if start > end => {
+                panic!("range start is greater than range end")
+            }
+            _ => (),
+        }
+
+        let front = self.find_lower_bound(range.start_bound());
+        let back = self.find_upper_bound(range.end_bound());
+
+        let (front, back) = match (front, back) {
+            (Some(front), Some(back)) if self.node(front).key <= self.node(back).key => {
+                (Some(front), Some(back))
+            }
+            _ => (None, None),
+        };
+
+        Range {
+            tree: self,
+            front,
+            back,
+        }
+    }
+
+    // O(n): the keys past `key` move into a fresh arena and both halves are
+    // rebuilt.
+    pub fn split_off<Q>(&mut self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut keys = std::mem::take(self).into_sorted_vec();
+        let right = keys.split_off(keys.partition_point(|other| other.borrow() < key));
+
+        *self = Self::from_sorted_vec(keys);
+        Self::from_sorted_vec(right)
+    }
+
+    // Same cost as `union_with`.
+    pub fn append(&mut self, other: &mut Self) {
+        self.union_with(std::mem::take(other));
+    }
+
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, Iter<'a, K>> {
+        Union {
+            iter: self.iter().peekable(),
+            other: other.iter().peekable(),
+            comparator: &Natural,
+        }
+    }
+
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, Iter<'a, K>> {
+        Intersection {
+            iter: self.iter().peekable(),
+            other: other.iter().peekable(),
+            comparator: &Natural,
+        }
+    }
+
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, Iter<'a, K>> {
+        Difference {
+            iter: self.iter().peekable(),
+            other: other.iter().peekable(),
+            comparator: &Natural,
+        }
+    }
+
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> SymmetricDifference<'a, Iter<'a, K>> {
+        SymmetricDifference {
+            iter: self.iter().peekable(),
+            other: other.iter().peekable(),
+            comparator: &Natural,
+        }
+    }
+
+    // O(n + m) rebuild, or O(m log n) when `other` is much smaller.
+    pub fn union_with(&mut self, other: Self) {
+        self.merge_with(other, SetOp::Union);
+    }
+
+    // O(n + m) rebuild, or O(m log n) when `other` is much smaller.
+    pub fn intersect_with(&mut self, other: Self) {
+        self.merge_with(other, SetOp::Intersection);
+    }
+
+    // O(n + m) rebuild, or O(m log n) when `other` is much smaller.
+    pub fn difference_with(&mut self, other: Self) {
+        self.merge_with(other, SetOp::Difference);
+    }
+
+    // O(n + m) rebuild, or O(m log n) when `other` is much smaller.
+    pub fn symmetric_difference_with(&mut self, other: Self) {
+        self.merge_with(other, SetOp::SymmetricDifference);
+    }
+
+    fn merge_with(&mut self, other: Self, op: SetOp) {
+        if other
+            .len()
+            .saturating_mul((self.len() + 1).ilog2() as usize)
+            < self.len()
+        {
+            self.merge_small(other, op);
+            return;
+        }
+
+        let mut keys = std::mem::take(self)
+            .into_sorted_vec()
+            .into_iter()
+            .peekable();
+        let mut other = other.into_sorted_vec().into_iter().peekable();
+        let mut merged = Vec::with_capacity(keys.len() + other.len());
+
+        loop {
+            let ordering = match (keys.peek(), other.peek()) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(key), Some(other_key)) => key.cmp(other_key),
+            };
+
+            match ordering {
+                Ordering::Less => {
+                    let key = keys.next().unwrap();
+                    if !matches!(op, SetOp::Intersection) {
+                        merged.push(key);
+                    }
+                }
+                Ordering::Greater => {
+                    let key = other.next().unwrap();
+                    if matches!(op, SetOp::Union | SetOp::SymmetricDifference) {
+                        merged.push(key);
+                    }
+                }
+                Ordering::Equal => {
+                    other.next();
+                    let key = keys.next().unwrap();
+                    if matches!(op, SetOp::Union | SetOp::Intersection) {
+                        merged.push(key);
+                    }
+                }
+            }
+        }
+
+        *self = Self::from_sorted_vec(merged);
+    }
+
+    // Applies each key of a small `other` directly. Its arena order is
+    // arbitrary, which only matters for the intersection, so that one sorts
+    // the keys it keeps before rebuilding.
+    fn merge_small(&mut self, other: Self, op: SetOp) {
+        let keys = other.nodes.into_iter().map(|node| node.key);
+
+        match op {
+            SetOp::Union => {
+                for key in keys {
+                    self.insert(key);
+                }
+            }
+            SetOp::Intersection => {
+                let mut kept: Vec<K> = keys.filter_map(|key| self.delete(&key)).collect();
+                kept.sort_unstable();
+                *self = Self::from_sorted_vec(kept);
+            }
+            SetOp::Difference => {
+                for key in keys {
+                    self.delete(&key);
+                }
+            }
+            SetOp::SymmetricDifference => {
+                for key in keys {
+                    if self.delete(&key).is_none() {
+                        self.insert(key);
+                    }
+                }
+            }
+        }
+    }
+
+    // O(n): the surviving keys are rebuilt into a fresh arena.
+    pub fn retain<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let mut keys = std::mem::take(self).into_sorted_vec();
+        keys.retain(|key| pred(key));
+
+        *self = Self::from_sorted_vec(keys);
+    }
+
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, F>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        ExtractIf {
+            next: self.root.map(|root| self.minimum(root)),
+            tree: self,
+            pred,
+        }
+    }
+
+    pub fn lower_bound<Q>(&self, bound: Bound<&Q>) -> Cursor<'_, K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        Cursor {
+            tree: self,
+            current: self.find_lower_bound(bound),
+        }
+    }
+
+    pub fn upper_bound<Q>(&self, bound: Bound<&Q>) -> Cursor<'_, K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        Cursor {
+            tree: self,
+            current: self.find_upper_bound(bound),
+        }
+    }
+
+    pub fn lower_bound_mut<Q>(&mut self, bound: Bound<&Q>) -> CursorMut<'_, K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        CursorMut {
+            current: self.find_lower_bound(bound),
+            tree: self,
+        }
+    }
+
+    pub fn upper_bound_mut<Q>(&mut self, bound: Bound<&Q>) -> CursorMut<'_, K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        CursorMut {
+            current: self.find_upper_bound(bound),
+            tree: self,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), InvariantViolation> {
+        if let Some(root) = self.root {
+            if self.node(root).is_red() {
+                return Err(InvariantViolation::RedRoot);
+            }
+            if self.node(root).parent.is_some() {
+                return Err(InvariantViolation::BrokenParentLink);
+            }
+        }
+
+        self.validate_subtree(self.root, None, None)?;
+
+        if self.len() != self.size(self.root) {
+            return Err(InvariantViolation::SizeMismatch);
+        }
+
+        Ok(())
+    }
+
+    fn validate_subtree(
+        &self,
+        link: Link,
+        lower: Option<&K>,
+        upper: Option<&K>,
+    ) -> Result<usize, InvariantViolation> {
+        let Some(index) = link else {
+            return Ok(0);
+        };
+        let node = self.node(index);
+
+        if lower.is_some_and(|lower| &node.key <= lower)
+            || upper.is_some_and(|upper| &node.key >= upper)
+        {
+            return Err(InvariantViolation::KeyOrder);
+        }
+
+        for child in [node.left, node.right].into_iter().flatten() {
+            if self.node(child).parent != link {
+                return Err(InvariantViolation::BrokenParentLink);
+            }
+            if node.is_red() && self.node(child).is_red() {
+                return Err(InvariantViolation::RedChildOfRed);
+            }
+        }
+
+        let left_height = self.validate_subtree(node.left, lower, Some(&node.key))?;
+        let right_height = self.validate_subtree(node.right, Some(&node.key), upper)?;
+
+        if left_height != right_height {
+            return Err(InvariantViolation::BlackHeightMismatch);
+        }
+        if node.size != self.size(node.left) + self.size(node.right) + 1 {
+            return Err(InvariantViolation::SizeMismatch);
+        }
+
+        Ok(left_height + node.is_black() as usize)
+    }
+}
+
+impl<'a, K> Shape<'a> for &'a ArenaRbTree<K> {
+    type Node = Index;
+    type Key = K;
+
+    fn left(self, index: Index) -> Link {
+        self.node(index).left
+    }
+
+    fn right(self, index: Index) -> Link {
+        self.node(index).right
+    }
+
+    fn parent(self, index: Index) -> Link {
+        self.node(index).parent
+    }
+
+    fn key(self, index: Index) -> &'a K {
+        &self.node(index).key
+    }
+
+    fn is_red(self, index: Index) -> bool {
+        self.node(index).is_red()
+    }
+}
+
+impl<K: Ord> Default for ArenaRbTree<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: PartialEq> PartialEq for ArenaRbTree<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Eq> Eq for ArenaRbTree<K> {}
+
+impl<K: PartialOrd> PartialOrd for ArenaRbTree<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K: Ord> Ord for ArenaRbTree<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<K: Hash> Hash for ArenaRbTree<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for key in self {
+            key.hash(state);
+        }
+    }
+}
+
+impl<K: Debug> Debug for ArenaRbTree<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord> FromIterator<K> for ArenaRbTree<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<K: Ord> Extend<K> for ArenaRbTree<K> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        let mut keys: Vec<K> = iter.into_iter().collect();
+
+        // Sorted input can be built in one pass and merged, whether or not it
+        // overlaps the keys already here.
+        if keys.is_sorted() {
+            keys.dedup();
+            self.union_with(Self::from_sorted_vec(keys));
+        } else {
+            for key in keys {
+                self.insert(key);
+            }
+        }
+    }
+}
+
+pub struct Iter<'a, K> {
+    tree: &'a ArenaRbTree<K>,
+    front: Link,
+    back: Link,
+    len: usize,
+}
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let index = self.front?;
+        self.len -= 1;
+        self.front = self.tree.next_index(index);
+
+        Some(&self.tree.node(index).key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K> DoubleEndedIterator for Iter<'_, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let index = self.back?;
+        self.len -= 1;
+        self.back = self.tree.prev_index(index);
+
+        Some(&self.tree.node(index).key)
+    }
+}
+
+impl<K> ExactSizeIterator for Iter<'_, K> {}
+
+impl<K> FusedIterator for Iter<'_, K> {}
+
+impl<'a, K> IntoIterator for &'a ArenaRbTree<K> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Cursor<'a, K> {
+    tree: &'a ArenaRbTree<K>,
+    current: Link,
+}
+
+impl<K> Clone for Cursor<'_, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for Cursor<'_, K> {}
+
+impl<'a, K> Cursor<'a, K> {
+    pub fn key(&self) -> Option<&'a K> {
+        self.tree.key(self.current)
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = self.tree.next_link(self.current);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = self.tree.prev_link(self.current);
+    }
+
+    pub fn peek_next(&self) -> Option<&'a K> {
+        self.tree.key(self.tree.next_link(self.current))
+    }
+
+    pub fn peek_prev(&self) -> Option<&'a K> {
+        self.tree.key(self.tree.prev_link(self.current))
+    }
+}
+
+pub struct CursorMut<'a, K> {
+    tree: &'a mut ArenaRbTree<K>,
+    current: Link,
+}
+
+impl<K> CursorMut<'_, K> {
+    pub fn key(&self) -> Option<&K> {
+        self.tree.key(self.current)
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = self.tree.next_link(self.current);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = self.tree.prev_link(self.current);
+    }
+
+    pub fn peek_next(&self) -> Option<&K> {
+        self.as_cursor().peek_next()
+    }
+
+    pub fn peek_prev(&self) -> Option<&K> {
+        self.as_cursor().peek_prev()
+    }
+
+    pub fn as_cursor(&self) -> Cursor<'_, K> {
+        Cursor {
+            tree: self.tree,
+            current: self.current,
+        }
+    }
+}
+
+impl<K: Ord> CursorMut<'_, K> {
+    pub fn remove_current(&mut self) -> Option<K> {
+        let index = self.current?;
+        self.current = self.tree.next_index(index);
+
+        Some(self.tree.remove_tracking(index, &mut self.current))
+    }
+
+    pub fn insert_after(&mut self, key: K) -> Result<(), UnorderedKeyError> {
+        if self.key().is_some_and(|current| current >= &key)
+            || self.peek_next().is_some_and(|next| &key >= next)
+        {
+            return Err(UnorderedKeyError);
+        }
+
+        let tree = &mut *self.tree;
+        let (parent, is_left) = match self.current {
+            Some(index) => match tree.node(index).right {
+                None => (Some(index), false),
+                Some(right) => (Some(tree.minimum(right)), true),
+            },
+            None => (tree.root.map(|root| tree.minimum(root)), true),
+        };
+
+        tree.attach(parent, is_left, key);
+
+        Ok(())
+    }
+
+    pub fn insert_before(&mut self, key: K) -> Result<(), UnorderedKeyError> {
+        if self.key().is_some_and(|current| &key >= current)
+            || self.peek_prev().is_some_and(|prev| prev >= &key)
+        {
+            return Err(UnorderedKeyError);
+        }
+
+        let tree = &mut *self.tree;
+        let (parent, is_left) = match self.current {
+            Some(index) => match tree.node(index).left {
+                None => (Some(index), true),
+                Some(left) => (Some(tree.maximum(left)), false),
+            },
+            None => (tree.root.map(|root| tree.maximum(root)), false),
+        };
+
+        tree.attach(parent, is_left, key);
+
+        Ok(())
+    }
+}
+
+pub struct ExtractIf<'a, K, F> {
+    tree: &'a mut ArenaRbTree<K>,
+    next: Link,
+    pred: F,
+}
+
+impl<K: Ord, F: FnMut(&K) -> bool> Iterator for ExtractIf<'_, K, F> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(index) = self.next {
+            self.next = self.tree.next_index(index);
+
+            if (self.pred)(&self.tree.node(index).key) {
+                return Some(self.tree.remove_tracking(index, &mut self.next));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.tree.len()))
+    }
+}
+
+impl<K: Ord, F: FnMut(&K) -> bool> FusedIterator for ExtractIf<'_, K, F> {}
+
+pub struct Range<'a, K> {
+    tree: &'a ArenaRbTree<K>,
+    front: Link,
+    back: Link,
+}
+
+impl<'a, K> Iterator for Range<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.front?;
+
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.front = self.tree.next_index(index);
+        }
+
+        Some(&self.tree.node(index).key)
+    }
+}
+
+impl<K> DoubleEndedIterator for Range<'_, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let index = self.back?;
+
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.back = self.tree.prev_index(index);
+        }
+
+        Some(&self.tree.node(index).key)
+    }
+}
+
+impl<K> FusedIterator for Range<'_, K> {}
@@ -0,0 +1,502 @@
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt::{self, Debug},
+    iter::FusedIterator,
+    sync::Arc,
+};
+
+use super::InvariantViolation;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Color {
+    Black,
+    Red,
+}
+
+#[derive(Clone)]
+struct Node<K> {
+    color: Color,
+    key: K,
+    left: Link<K>,
+    right: Link<K>,
+}
+
+type Link<K> = Option<Arc<Node<K>>>;
+
+fn is_red<K>(link: &Link<K>) -> bool {
+    link.as_ref().is_some_and(|node| node.color == Color::Red)
+}
+
+fn has_red_child<K>(link: &Link<K>) -> bool {
+    link.as_ref()
+        .is_some_and(|node| is_red(&node.left) || is_red(&node.right))
+}
+
+fn node_mut<K: Clone>(link: &mut Link<K>) -> &mut Node<K> {
+    Arc::make_mut(link.as_mut().unwrap())
+}
+
+pub struct PersistentRbTree<K> {
+    root: Link<K>,
+    len: usize,
+}
+
+impl<K> PersistentRbTree<K> {
+    pub fn new() -> Self
+    where
+        K: Ord,
+    {
+        Self { root: None, len: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn iter(&self) -> Iter<'_, K> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            len: self.len,
+        };
+        iter.push_left(&self.root);
+        iter
+    }
+
+    pub fn shares_root_with(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(root), Some(other_root)) => Arc::ptr_eq(root, other_root),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut current = &self.root;
+        while let Some(node) = current {
+            current = match key.cmp(node.key.borrow()) {
+                Ordering::Equal => return Some(&node.key),
+                Ordering::Less => &node.left,
+                Ordering::Greater => &node.right,
+            }
+        }
+        None
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn insert(&self, key: K) -> Self
+    where
+        K: Ord + Clone,
+    {
+        if self.contains(&key) {
+            return self.clone();
+        }
+
+        let mut root = self.root.clone();
+        Self::insert_at(&mut root, key);
+        node_mut(&mut root).color = Color::Black;
+
+        Self {
+            root,
+            len: self.len + 1,
+        }
+    }
+
+    fn insert_at(link: &mut Link<K>, key: K)
+    where
+        K: Ord + Clone,
+    {
+        if link.is_none() {
+            *link = Some(Arc::new(Node {
+                color: Color::Red,
+                key,
+                left: None,
+                right: None,
+            }));
+            return;
+        }
+
+        let node = node_mut(link);
+        if key < node.key {
+            Self::insert_at(&mut node.left, key);
+        } else {
+            Self::insert_at(&mut node.right, key);
+        }
+
+        Self::balance_insert(link);
+    }
+
+    fn balance_insert(link: &mut Link<K>)
+    where
+        K: Clone,
+    {
+        let node = node_mut(link);
+
+        let is_left = if is_red(&node.left) && has_red_child(&node.left) {
+            true
+        } else if is_red(&node.right) && has_red_child(&node.right) {
+            false
+        } else {
+            return;
+        };
+
+        let uncle = if is_left {
+            &mut node.right
+        } else {
+            &mut node.left
+        };
+        if is_red(uncle) {
+            node_mut(uncle).color = Color::Black;
+            let parent = if is_left {
+                &mut node.left
+            } else {
+                &mut node.right
+            };
+            node_mut(parent).color = Color::Black;
+            node.color = Color::Red;
+            return;
+        }
+
+        if is_left {
+            if is_red(&node.left.as_ref().unwrap().right) {
+                Self::rotate_left(&mut node.left);
+            }
+            node_mut(&mut node.left).color = Color::Black;
+            node.color = Color::Red;
+            Self::rotate_right(link);
+        } else {
+            if is_red(&node.right.as_ref().unwrap().left) {
+                Self::rotate_right(&mut node.right);
+            }
+            node_mut(&mut node.right).color = Color::Black;
+            node.color = Color::Red;
+            Self::rotate_left(link);
+        }
+    }
+
+    pub fn delete<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q> + Clone,
+        Q: Ord + ?Sized,
+    {
+        if !self.contains(key) {
+            return self.clone();
+        }
+
+        let mut root = self.root.clone();
+        Self::delete_at(&mut root, key);
+        if root.is_some() {
+            node_mut(&mut root).color = Color::Black;
+        }
+
+        Self {
+            root,
+            len: self.len - 1,
+        }
+    }
+
+    fn delete_at<Q>(link: &mut Link<K>, key: &Q) -> bool
+    where
+        K: Borrow<Q> + Clone,
+        Q: Ord + ?Sized,
+    {
+        let node = node_mut(link);
+
+        match key.cmp(node.key.borrow()) {
+            Ordering::Less => {
+                Self::delete_at(&mut node.left, key) && Self::balance_delete(link, true)
+            }
+            Ordering::Greater => {
+                Self::delete_at(&mut node.right, key) && Self::balance_delete(link, false)
+            }
+            Ordering::Equal if node.left.is_some() && node.right.is_some() => {
+                let (min_key, deficit) = Self::delete_min(&mut node.right);
+                node.key = min_key;
+
+                deficit && Self::balance_delete(link, false)
+            }
+            Ordering::Equal => Self::remove_node(link).1,
+        }
+    }
+
+    fn delete_min(link: &mut Link<K>) -> (K, bool)
+    where
+        K: Clone,
+    {
+        let node = node_mut(link);
+
+        if node.left.is_some() {
+            let (key, deficit) = Self::delete_min(&mut node.left);
+
+            (key, deficit && Self::balance_delete(link, true))
+        } else {
+            Self::remove_node(link)
+        }
+    }
+
+    fn remove_node(link: &mut Link<K>) -> (K, bool)
+    where
+        K: Clone,
+    {
+        let node = Arc::unwrap_or_clone(link.take().unwrap());
+
+        let mut child = node.left.or(node.right);
+        let deficit = if node.color == Color::Red {
+            false
+        } else if is_red(&child) {
+            node_mut(&mut child).color = Color::Black;
+            false
+        } else {
+            true
+        };
+
+        *link = child;
+
+        (node.key, deficit)
+    }
+
+    fn balance_delete(link: &mut Link<K>, is_left_child: bool) -> bool
+    where
+        K: Clone,
+    {
+        let node = node_mut(link);
+        let sibling = if is_left_child {
+            &mut node.right
+        } else {
+            &mut node.left
+        };
+
+        if is_red(sibling) {
+            node_mut(sibling).color = Color::Black;
+            node.color = Color::Red;
+
+            if is_left_child {
+                Self::rotate_left(link);
+                Self::balance_delete(&mut node_mut(link).left, true)
+            } else {
+                Self::rotate_right(link);
+                Self::balance_delete(&mut node_mut(link).right, false)
+            };
+
+            return false;
+        }
+
+        let sibling_node = node_mut(sibling);
+        let (primary_nephew, secondary_nephew) = if is_left_child {
+            (&sibling_node.left, &sibling_node.right)
+        } else {
+            (&sibling_node.right, &sibling_node.left)
+        };
+
+        if !is_red(primary_nephew) && !is_red(secondary_nephew) {
+            sibling_node.color = Color::Red;
+
+            if node.color == Color::Red {
+                node.color = Color::Black;
+                return false;
+            }
+            return true;
+        }
+
+        if !is_red(secondary_nephew) {
+            if is_left_child {
+                node_mut(&mut sibling_node.left).color = Color::Black;
+                sibling_node.color = Color::Red;
+                Self::rotate_right(sibling);
+            } else {
+                node_mut(&mut sibling_node.right).color = Color::Black;
+                sibling_node.color = Color::Red;
+                Self::rotate_left(sibling);
+            }
+        }
+
+        let sibling_node = node_mut(sibling);
+        sibling_node.color = node.color;
+        node.color = Color::Black;
+
+        if is_left_child {
+            node_mut(&mut sibling_node.right).color = Color::Black;
+            Self::rotate_left(link);
+        } else {
+            node_mut(&mut sibling_node.left).color = Color::Black;
+            Self::rotate_right(link);
+        }
+
+        false
+    }
+
+    fn rotate_left(link: &mut Link<K>)
+    where
+        K: Clone,
+    {
+        let mut node = link.take().unwrap();
+        let node_ref = Arc::make_mut(&mut node);
+
+        let mut right = node_ref.right.take().unwrap();
+        let right_ref = Arc::make_mut(&mut right);
+
+        node_ref.right = right_ref.left.take();
+        right_ref.left = Some(node);
+
+        *link = Some(right);
+    }
+
+    fn rotate_right(link: &mut Link<K>)
+    where
+        K: Clone,
+    {
+        let mut node = link.take().unwrap();
+        let node_ref = Arc::make_mut(&mut node);
+
+        let mut left = node_ref.left.take().unwrap();
+        let left_ref = Arc::make_mut(&mut left);
+
+        node_ref.left = left_ref.right.take();
+        left_ref.right = Some(node);
+
+        *link = Some(left);
+    }
+
+    pub fn validate(&self) -> Result<(), InvariantViolation>
+    where
+        K: Ord,
+    {
+        if is_red(&self.root) {
+            return Err(InvariantViolation::RedRoot);
+        }
+
+        Self::validate_subtree(&self.root, None, None)?;
+
+        if self.len != self.iter().count() {
+            return Err(InvariantViolation::SizeMismatch);
+        }
+
+        Ok(())
+    }
+
+    fn validate_subtree(
+        link: &Link<K>,
+        lower: Option<&K>,
+        upper: Option<&K>,
+    ) -> Result<usize, InvariantViolation>
+    where
+        K: Ord,
+    {
+        let Some(node) = link else {
+            return Ok(0);
+        };
+
+        if lower.is_some_and(|lower| &node.key <= lower)
+            || upper.is_some_and(|upper| &node.key >= upper)
+        {
+            return Err(InvariantViolation::KeyOrder);
+        }
+
+        if node.color == Color::Red && (is_red(&node.left) || is_red(&node.right)) {
+            return Err(InvariantViolation::RedChildOfRed);
+        }
+
+        let left_height = Self::validate_subtree(&node.left, lower, Some(&node.key))?;
+        let right_height = Self::validate_subtree(&node.right, Some(&node.key), upper)?;
+
+        if left_height != right_height {
+            return Err(InvariantViolation::BlackHeightMismatch);
+        }
+
+        Ok(left_height + (node.color == Color::Black) as usize)
+    }
+}
+
+impl<K> Clone for PersistentRbTree<K> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<K: Ord> Default for PersistentRbTree<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: PartialEq> PartialEq for PersistentRbTree<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.shares_root_with(other) || (self.len == other.len && self.iter().eq(other.iter()))
+    }
+}
+
+impl<K: Eq> Eq for PersistentRbTree<K> {}
+
+impl<K: Debug> Debug for PersistentRbTree<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord + Clone> FromIterator<K> for PersistentRbTree<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::new(), |tree, key| tree.insert(key))
+    }
+}
+
+pub struct Iter<'a, K> {
+    stack: Vec<&'a Node<K>>,
+    len: usize,
+}
+
+impl<'a, K> Iter<'a, K> {
+    fn push_left(&mut self, mut link: &'a Link<K>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.len -= 1;
+        self.push_left(&node.right);
+
+        Some(&node.key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K> ExactSizeIterator for Iter<'_, K> {}
+
+impl<K> FusedIterator for Iter<'_, K> {}
+
+impl<'a, K> IntoIterator for &'a PersistentRbTree<K> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,98 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::{Augment, Comparator, RbMap, RbTree};
+
+const MAX_PREALLOCATED: usize = 4096;
+
+impl<K: Serialize, V: Serialize, A, C> Serialize for RbMap<K, V, A, C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de, K, V, A, C> Deserialize<'de> for RbMap<K, V, A, C>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    A: Augment<K>,
+    C: Comparator<K> + Default + Clone,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MapVisitor<K, V, A, C>(PhantomData<(K, V, A, C)>);
+
+        impl<'de, K, V, A, C> Visitor<'de> for MapVisitor<K, V, A, C>
+        where
+            K: Deserialize<'de>,
+            V: Deserialize<'de>,
+            A: Augment<K>,
+            C: Comparator<K> + Default + Clone,
+        {
+            type Value = RbMap<K, V, A, C>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Self::Value, M::Error> {
+                let capacity = access.size_hint().unwrap_or(0).min(MAX_PREALLOCATED);
+                let mut entries = Vec::with_capacity(capacity);
+                while let Some(entry) = access.next_entry()? {
+                    entries.push(entry);
+                }
+
+                // Serialized maps are already sorted, so this takes the
+                // linear-time bulk build instead of inserting one by one.
+                Ok(entries.into_iter().collect())
+            }
+        }
+
+        deserializer.deserialize_map(MapVisitor(PhantomData))
+    }
+}
+
+impl<K: Serialize, A, C> Serialize for RbTree<K, A, C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, K, A, C> Deserialize<'de> for RbTree<K, A, C>
+where
+    K: Deserialize<'de>,
+    A: Augment<K>,
+    C: Comparator<K> + Default + Clone,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SeqVisitor<K, A, C>(PhantomData<(K, A, C)>);
+
+        impl<'de, K, A, C> Visitor<'de> for SeqVisitor<K, A, C>
+        where
+            K: Deserialize<'de>,
+            A: Augment<K>,
+            C: Comparator<K> + Default + Clone,
+        {
+            type Value = RbTree<K, A, C>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a sequence")
+            }
+
+            fn visit_seq<S: SeqAccess<'de>>(self, mut access: S) -> Result<Self::Value, S::Error> {
+                let capacity = access.size_hint().unwrap_or(0).min(MAX_PREALLOCATED);
+                let mut keys = Vec::with_capacity(capacity);
+                while let Some(key) = access.next_element()? {
+                    keys.push(key);
+                }
+
+                Ok(keys.into_iter().collect())
+            }
+        }
+
+        deserializer.deserialize_seq(SeqVisitor(PhantomData))
+    }
+}
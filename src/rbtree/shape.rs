@@ -0,0 +1,266 @@
+use std::fmt::Debug;
+
+// Read access to a red-black tree's links. The pointer and arena trees both
+// implement it, so traversal, rendering and cursor movement are written once.
+pub(super) trait Shape<'a>: Copy {
+    type Node: Copy + PartialEq;
+    type Key: 'a;
+
+    fn left(self, node: Self::Node) -> Option<Self::Node>;
+    fn right(self, node: Self::Node) -> Option<Self::Node>;
+    fn parent(self, node: Self::Node) -> Option<Self::Node>;
+    fn key(self, node: Self::Node) -> &'a Self::Key;
+    fn is_red(self, node: Self::Node) -> bool;
+}
+
+pub(super) fn minimum<'a, S: Shape<'a>>(shape: S, mut node: S::Node) -> S::Node {
+    while let Some(left) = shape.left(node) {
+        node = left;
+    }
+    node
+}
+
+pub(super) fn maximum<'a, S: Shape<'a>>(shape: S, mut node: S::Node) -> S::Node {
+    while let Some(right) = shape.right(node) {
+        node = right;
+    }
+    node
+}
+
+pub(super) fn successor<'a, S: Shape<'a>>(shape: S, node: S::Node) -> Option<S::Node> {
+    if let Some(right) = shape.right(node) {
+        return Some(minimum(shape, right));
+    }
+
+    let mut child = node;
+    let mut parent = shape.parent(child);
+    while let Some(parent_node) = parent {
+        if shape.left(parent_node) == Some(child) {
+            break;
+        }
+        child = parent_node;
+        parent = shape.parent(parent_node);
+    }
+    parent
+}
+
+pub(super) fn predecessor<'a, S: Shape<'a>>(shape: S, node: S::Node) -> Option<S::Node> {
+    if let Some(left) = shape.left(node) {
+        return Some(maximum(shape, left));
+    }
+
+    let mut child = node;
+    let mut parent = shape.parent(child);
+    while let Some(parent_node) = parent {
+        if shape.right(parent_node) == Some(child) {
+            break;
+        }
+        child = parent_node;
+        parent = shape.parent(parent_node);
+    }
+    parent
+}
+
+// Cursors use `None` for the ghost position between the last and the first
+// key, so stepping from it wraps around to the other end.
+pub(super) fn next<'a, S: Shape<'a>>(
+    shape: S,
+    root: Option<S::Node>,
+    current: Option<S::Node>,
+) -> Option<S::Node> {
+    match current {
+        Some(node) => successor(shape, node),
+        None => root.map(|root| minimum(shape, root)),
+    }
+}
+
+pub(super) fn prev<'a, S: Shape<'a>>(
+    shape: S,
+    root: Option<S::Node>,
+    current: Option<S::Node>,
+) -> Option<S::Node> {
+    match current {
+        Some(node) => predecessor(shape, node),
+        None => root.map(|root| maximum(shape, root)),
+    }
+}
+
+pub(super) fn inorder<'a, S: Shape<'a>>(
+    shape: S,
+    root: Option<S::Node>,
+    len: usize,
+) -> Vec<&'a S::Key> {
+    let mut result = Vec::with_capacity(len);
+    let mut stack = Vec::new();
+    let mut current = root;
+
+    loop {
+        while let Some(node) = current {
+            stack.push(node);
+            current = shape.left(node);
+        }
+
+        let Some(node) = stack.pop() else {
+            break;
+        };
+
+        result.push(shape.key(node));
+        current = shape.right(node);
+    }
+
+    result
+}
+
+pub(super) fn preorder<'a, S: Shape<'a>>(
+    shape: S,
+    root: Option<S::Node>,
+    len: usize,
+) -> Vec<&'a S::Key> {
+    let mut result = Vec::with_capacity(len);
+    let mut stack: Vec<S::Node> = root.into_iter().collect();
+
+    while let Some(node) = stack.pop() {
+        result.push(shape.key(node));
+        stack.extend(shape.right(node));
+        stack.extend(shape.left(node));
+    }
+
+    result
+}
+
+pub(super) fn postorder<'a, S: Shape<'a>>(
+    shape: S,
+    root: Option<S::Node>,
+    len: usize,
+) -> Vec<&'a S::Key> {
+    let mut result = Vec::with_capacity(len);
+    let mut stack: Vec<S::Node> = root.into_iter().collect();
+
+    while let Some(node) = stack.pop() {
+        result.push(shape.key(node));
+        stack.extend(shape.left(node));
+        stack.extend(shape.right(node));
+    }
+
+    result.reverse();
+    result
+}
+
+pub(super) fn level_order<'a, S: Shape<'a>>(
+    shape: S,
+    root: Option<S::Node>,
+) -> Vec<Vec<&'a S::Key>> {
+    let mut levels = Vec::new();
+    let mut level: Vec<S::Node> = root.into_iter().collect();
+
+    while !level.is_empty() {
+        let mut next_level = Vec::with_capacity(2 * level.len());
+        let keys = level
+            .into_iter()
+            .map(|node| {
+                next_level.extend(shape.left(node));
+                next_level.extend(shape.right(node));
+                shape.key(node)
+            })
+            .collect();
+
+        levels.push(keys);
+        level = next_level;
+    }
+
+    levels
+}
+
+pub(super) fn to_dot<'a, S>(shape: S, root: Option<S::Node>) -> String
+where
+    S: Shape<'a>,
+    S::Key: Debug,
+{
+    let mut dot = String::from("digraph RbTree {\n");
+    dot.push_str("    node [shape=circle, style=filled, fontcolor=white];\n");
+
+    let mut next_id = 0;
+    let mut stack = Vec::new();
+    if let Some(root) = root {
+        stack.push((root, next_id));
+        next_id += 1;
+    }
+
+    while let Some((node, id)) = stack.pop() {
+        let label = format!("{:?}", shape.key(node))
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        let color = if shape.is_red(node) { "red" } else { "black" };
+        dot.push_str(&format!(
+            "    n{id} [label=\"{label}\", fillcolor={color}];\n"
+        ));
+
+        let children = [shape.left(node), shape.right(node)];
+        if children.iter().all(Option::is_none) {
+            continue;
+        }
+
+        for child in children {
+            match child {
+                Some(child) => {
+                    dot.push_str(&format!("    n{id} -> n{next_id};\n"));
+                    stack.push((child, next_id));
+                }
+                None => {
+                    dot.push_str(&format!("    nil{next_id} [shape=point];\n"));
+                    dot.push_str(&format!("    n{id} -> nil{next_id};\n"));
+                }
+            }
+            next_id += 1;
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+pub(super) fn to_pretty_string<'a, S>(shape: S, root: Option<S::Node>) -> String
+where
+    S: Shape<'a>,
+    S::Key: Debug,
+{
+    enum Step<N> {
+        Visit(Option<N>, String, Option<bool>),
+        Emit(N, String, Option<bool>),
+    }
+
+    let mut output = String::new();
+    let mut stack = vec![Step::Visit(root, String::new(), None)];
+
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Visit(None, _, _) => (),
+            Step::Visit(Some(node), prefix, is_right) => {
+                let (right_prefix, left_prefix) = match is_right {
+                    None => (String::new(), String::new()),
+                    Some(true) => (format!("{prefix}    "), format!("{prefix}│   ")),
+                    Some(false) => (format!("{prefix}│   "), format!("{prefix}    ")),
+                };
+
+                stack.push(Step::Visit(shape.left(node), left_prefix, Some(false)));
+                stack.push(Step::Emit(node, prefix, is_right));
+                stack.push(Step::Visit(shape.right(node), right_prefix, Some(true)));
+            }
+            Step::Emit(node, prefix, is_right) => {
+                let branch = match is_right {
+                    None => "",
+                    Some(true) => "┌── ",
+                    Some(false) => "└── ",
+                };
+                let color = if shape.is_red(node) { 'R' } else { 'B' };
+
+                output.push_str(&format!(
+                    "{prefix}{branch}{:?} [{color}]\n",
+                    shape.key(node)
+                ));
+            }
+        }
+    }
+
+    output
+}
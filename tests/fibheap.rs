@@ -1,7 +1,6 @@
-use num::traits::bounds::Bounded;
-use std::fmt::Debug;
+use std::{collections::BTreeSet, fmt::Debug};
 
-use nasp_homework::fibheap::FibHeap;
+use nasp_homework::fibheap::{DecreaseKeyError, FibHeap};
 use quickcheck_macros::*;
 
 #[quickcheck]
@@ -70,6 +69,141 @@ fn union(xs: Vec<u32>) {
     assert_heap_vec_eq(heap, comb);
 }
 
+#[quickcheck]
+fn decrease_key_after_pop(mut elements: Vec<u32>) {
+    elements.sort();
+    elements.dedup();
+
+    if elements.len() < 2 || elements[0] == 0 {
+        return;
+    }
+
+    let mut heap = FibHeap::new();
+    let handles: Vec<_> = elements
+        .iter()
+        .map(|&element| heap.push_with_handle(element))
+        .collect();
+
+    assert_eq!(heap.pop(), Some(elements[0]));
+
+    let new_min = elements[0] - 1;
+    let last = *handles.last().unwrap();
+
+    assert_eq!(heap.decrease_key(last, new_min), Ok(()));
+    assert_eq!(heap.get(last), Some(&new_min));
+    assert_eq!(heap.peek(), Some(&new_min));
+    assert_eq!(
+        heap.decrease_key(handles[0], 0),
+        Err(DecreaseKeyError::StaleHandle)
+    );
+}
+
+#[quickcheck]
+fn remove_by_handle(elements: Vec<u32>) {
+    if elements.len() < 2 {
+        return;
+    }
+
+    let mut heap = FibHeap::new();
+    let handles: Vec<_> = elements
+        .iter()
+        .map(|&element| heap.push_with_handle(element))
+        .collect();
+
+    heap.pop();
+
+    let mut expected = elements.clone();
+    expected.sort();
+    expected.remove(0);
+
+    let removed = heap.remove(handles[elements.len() / 2]);
+    if let Some(value) = removed {
+        let position = expected.iter().position(|&x| x == value).unwrap();
+        expected.remove(position);
+    }
+
+    assert_eq!(heap.len(), expected.len());
+
+    expected.reverse();
+    assert_heap_vec_eq(heap, expected);
+}
+
+#[quickcheck]
+fn handles_match_model(ops: Vec<(u8, u16)>) {
+    let mut heap = FibHeap::new();
+    let mut handles = Vec::new();
+    let mut model = BTreeSet::new();
+
+    for (op, x) in ops {
+        match op % 4 {
+            0 | 1 => {
+                let id = handles.len();
+                handles.push(heap.push_with_handle((x, id)));
+                model.insert((x, id));
+            }
+            2 => assert_eq!(heap.pop(), model.pop_first()),
+            _ if handles.is_empty() => (),
+            _ => {
+                let id = x as usize % handles.len();
+                let current = model.iter().find(|&&(_, other)| other == id).copied();
+
+                match current {
+                    Some((value, _)) if x % 2 == 0 => {
+                        let lowered = (value / 2, id);
+                        assert_eq!(heap.decrease_key(handles[id], lowered), Ok(()));
+                        model.remove(&(value, id));
+                        model.insert(lowered);
+                    }
+                    Some((value, _)) if x % 3 == 0 => {
+                        assert_eq!(
+                            heap.decrease_key(handles[id], (value, id + 1)),
+                            Err(DecreaseKeyError::KeyIncreased)
+                        );
+                    }
+                    Some(entry) => {
+                        assert_eq!(heap.remove(handles[id]), Some(entry));
+                        model.remove(&entry);
+                    }
+                    None => {
+                        assert_eq!(heap.get(handles[id]), None);
+                        assert_eq!(
+                            heap.decrease_key(handles[id], (0, id)),
+                            Err(DecreaseKeyError::StaleHandle)
+                        );
+                    }
+                }
+            }
+        }
+
+        assert_eq!(heap.len(), model.len());
+        assert_eq!(heap.peek(), model.first());
+    }
+
+    while let Some(entry) = heap.pop() {
+        assert_eq!(Some(entry), model.pop_first());
+    }
+    assert!(model.is_empty());
+}
+
+#[quickcheck]
+fn union_keeps_first_handles(xs: Vec<u32>, ys: Vec<u32>) {
+    let mut first = FibHeap::new();
+    let handles: Vec<_> = xs.iter().map(|&x| first.push_with_handle(x)).collect();
+    first.pop();
+
+    let mut second: FibHeap<u32> = ys.iter().copied().collect();
+    second.pop();
+
+    let mut heap = FibHeap::union(first, second);
+
+    for handle in handles {
+        if heap.get(handle).is_some() {
+            assert_eq!(heap.decrease_key(handle, 0), Ok(()));
+            assert_eq!(heap.peek(), Some(&0));
+        }
+    }
+}
+
 fn pops_by_min_check(mut xs: Vec<u32>) {
     let mut heap = FibHeap::new();
 
@@ -83,7 +217,7 @@ fn pops_by_min_check(mut xs: Vec<u32>) {
     assert_heap_vec_eq(heap, xs);
 }
 
-fn assert_heap_vec_eq<T: Ord + Bounded + Clone + Debug>(mut heap: FibHeap<T>, mut vec: Vec<T>) {
+fn assert_heap_vec_eq<T: Ord + Debug>(mut heap: FibHeap<T>, mut vec: Vec<T>) {
     while let Some(b) = heap.pop() {
         let a = vec.pop();
         assert_eq!(a, Some(b), "should in pop ascending order");
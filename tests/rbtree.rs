@@ -1,4 +1,17 @@
-use nasp_homework::rbtree::RbTree;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BTreeSet, HashSet},
+    ops::Bound,
+    rc::Rc,
+    sync::Arc,
+    thread,
+};
+
+use nasp_homework::rbtree::{
+    ArenaRbTree, Augment, Comparator, ConcurrentRbTree, Entry, FnComparator, IntervalTree, Natural,
+    PersistentRbTree, RbMap, RbMultiSet, RbTree, UnorderedKeyError,
+};
+use quickcheck_macros::*;
 
 #[test]
 fn insert() {
@@ -16,7 +29,7 @@ fn insert() {
 
     elements.sort();
 
-    assert_eq!(result, elements);
+    assert_eq!(result, elements.iter().collect::<Vec<_>>());
 }
 
 #[test]
@@ -63,5 +76,1575 @@ fn delete() {
 
     let result = tree.inorder_traverse();
 
-    assert_eq!(result, elements)
+    assert_eq!(result, elements.iter().collect::<Vec<_>>())
+}
+
+#[test]
+fn delete_evens_then_odds() {
+    for len in 1..40u32 {
+        let mut tree: RbTree<u32> = RbTree::new();
+        let mut elements: Vec<u32> = (0..len).collect();
+
+        for element in elements.iter() {
+            tree.insert(*element);
+        }
+
+        let to_remove: Vec<u32> = (0..len).step_by(2).chain((1..len).step_by(2)).collect();
+
+        for element in to_remove.iter() {
+            assert_eq!(tree.delete(element), Some(*element));
+            elements.retain(|value| *value != *element);
+
+            assert_eq!(tree.inorder_traverse(), elements.iter().collect::<Vec<_>>());
+        }
+    }
+}
+
+#[test]
+fn delete_every_position() {
+    for len in 1..40u32 {
+        for removed in 0..len {
+            let mut tree: RbTree<u32> = RbTree::new();
+
+            for element in 0..len {
+                tree.insert(element);
+            }
+
+            assert_eq!(tree.delete(&removed), Some(removed));
+
+            let mut elements: Vec<u32> = (0..len).collect();
+            elements.retain(|value| *value != removed);
+
+            assert_eq!(tree.inorder_traverse(), elements.iter().collect::<Vec<_>>());
+        }
+    }
+}
+
+#[test]
+fn owned_keys() {
+    let mut tree: RbTree<String> = RbTree::new();
+
+    for word in ["pear", "apple", "fig", "banana", "cherry"] {
+        tree.insert(word.to_string());
+    }
+
+    assert!(!tree.insert("fig".to_string()));
+    assert!(tree.contains("apple"));
+
+    assert_eq!(tree.delete("pear"), Some("pear".to_string()));
+    assert_eq!(tree.delete("pear"), None);
+
+    assert_eq!(
+        tree.inorder_traverse(),
+        vec!["apple", "banana", "cherry", "fig"]
+    );
+}
+
+#[test]
+fn map_insert_get() {
+    let mut map: RbMap<u32, &str> = RbMap::new();
+
+    assert!(map.is_empty());
+
+    assert_eq!(map.insert(6, "six"), None);
+    assert_eq!(map.insert(2, "two"), None);
+    assert_eq!(map.insert(9, "nine"), None);
+    assert_eq!(map.insert(6, "SIX"), Some("six"));
+
+    assert_eq!(map.get(&6), Some(&"SIX"));
+    assert_eq!(map.get(&2), Some(&"two"));
+    assert_eq!(map.get(&7), None);
+
+    assert!(map.contains_key(&9));
+    assert!(!map.contains_key(&10));
+}
+
+#[test]
+fn map_get_mut_remove() {
+    let mut map: RbMap<String, u32> = RbMap::new();
+
+    for (i, word) in ["one", "two", "three", "four", "five"].iter().enumerate() {
+        map.insert(word.to_string(), i as u32);
+    }
+
+    if let Some(value) = map.get_mut("three") {
+        *value += 10;
+    }
+
+    assert_eq!(map.get("three"), Some(&12));
+
+    assert_eq!(map.remove("three"), Some(12));
+    assert_eq!(map.remove("three"), None);
+    assert!(!map.contains_key("three"));
+
+    assert_eq!(map.remove_entry("one"), Some(("one".to_string(), 0)));
+    assert_eq!(map.get("five"), Some(&4));
+}
+
+#[quickcheck]
+fn iter_matches_sorted(xs: Vec<u32>) {
+    let mut tree: RbTree<u32> = RbTree::new();
+
+    for x in &xs {
+        tree.insert(*x);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    let mut elements = xs.clone();
+    elements.sort();
+    elements.dedup();
+
+    assert_eq!(tree.len(), elements.len());
+    assert_eq!(tree.iter().len(), elements.len());
+    assert!(tree.iter().eq(elements.iter()));
+    assert!(tree.iter().rev().eq(elements.iter().rev()));
+}
+
+#[test]
+fn iter_both_ends() {
+    let mut tree: RbTree<u32> = RbTree::new();
+
+    for element in [6, 11, 10, 2, 9, 7, 5] {
+        tree.insert(element);
+    }
+
+    let mut iter = tree.iter();
+
+    assert_eq!(iter.next(), Some(&2));
+    assert_eq!(iter.next_back(), Some(&11));
+    assert_eq!(iter.len(), 5);
+    assert_eq!(iter.next_back(), Some(&10));
+    assert_eq!(iter.next(), Some(&5));
+    assert_eq!(iter.collect::<Vec<_>>(), vec![&6, &7, &9]);
+
+    let mut visited = Vec::new();
+    for key in &tree {
+        visited.push(*key);
+    }
+
+    assert_eq!(visited, vec![2, 5, 6, 7, 9, 10, 11]);
+}
+
+#[test]
+fn map_iter() {
+    let mut map: RbMap<u32, char> = RbMap::new();
+
+    for (key, value) in [(3, 'c'), (1, 'a'), (2, 'b')] {
+        map.insert(key, value);
+    }
+
+    map.remove(&2);
+
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&1, &'a'), (&3, &'c')]);
+    assert_eq!((&map).into_iter().next_back(), Some((&3, &'c')));
+}
+
+#[quickcheck]
+fn range_matches_filter(xs: Vec<u8>, start: u8, end: u8) {
+    let (start, end) = if start <= end {
+        (start, end)
+    } else {
+        (end, start)
+    };
+
+    let mut tree: RbTree<u8> = RbTree::new();
+
+    for x in &xs {
+        tree.insert(*x);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    let mut elements = xs.clone();
+    elements.sort();
+    elements.dedup();
+
+    let expected: Vec<&u8> = elements
+        .iter()
+        .filter(|x| (start..end).contains(*x))
+        .collect();
+    assert_eq!(tree.range(start..end).collect::<Vec<_>>(), expected);
+
+    let expected: Vec<&u8> = elements
+        .iter()
+        .filter(|x| (start..=end).contains(*x))
+        .collect();
+    assert_eq!(tree.range(start..=end).collect::<Vec<_>>(), expected);
+    assert!(tree.range(start..=end).rev().eq(expected.into_iter().rev()));
+
+    let expected: Vec<&u8> = elements.iter().filter(|x| **x > start).collect();
+    let bounds = (Bound::Excluded(start), Bound::Unbounded);
+    assert_eq!(tree.range(bounds).collect::<Vec<_>>(), expected);
+
+    let expected: Vec<&u8> = elements.iter().filter(|x| **x < end).collect();
+    assert_eq!(tree.range(..end).collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn range_bounds() {
+    let mut tree: RbTree<String> = RbTree::new();
+
+    for word in ["pear", "apple", "fig", "banana", "cherry"] {
+        tree.insert(word.to_string());
+    }
+
+    assert_eq!(
+        tree.range::<str, _>((Bound::Included("b"), Bound::Excluded("d")))
+            .collect::<Vec<_>>(),
+        vec!["banana", "cherry"]
+    );
+    assert_eq!(
+        tree.range::<str, _>((Bound::Included("fig"), Bound::Unbounded))
+            .collect::<Vec<_>>(),
+        vec!["fig", "pear"]
+    );
+    assert_eq!(
+        tree.range::<str, _>((Bound::Excluded("x"), Bound::Unbounded))
+            .count(),
+        0
+    );
+    assert_eq!(tree.range::<String, _>(..).count(), 5);
+
+    let mut range = tree.range::<str, _>((Bound::Included("apple"), Bound::Excluded("pear")));
+
+    assert_eq!(range.next_back(), Some(&"fig".to_string()));
+    assert_eq!(range.next(), Some(&"apple".to_string()));
+    assert_eq!(range.next(), Some(&"banana".to_string()));
+    assert_eq!(range.next_back(), Some(&"cherry".to_string()));
+    assert_eq!(range.next(), None);
+    assert_eq!(range.next_back(), None);
+}
+
+#[test]
+#[should_panic(expected = "range start is greater than range end")]
+fn range_start_after_end() {
+    let mut tree: RbTree<u32> = RbTree::new();
+    tree.insert(1);
+
+    tree.range((Bound::Included(5), Bound::Excluded(2)));
+}
+
+#[quickcheck]
+fn select_matches_sorted(xs: Vec<u16>) {
+    let removed: Vec<u16> = xs.iter().step_by(3).copied().collect();
+
+    let mut tree: RbTree<u16> = RbTree::new();
+
+    for x in &xs {
+        tree.insert(*x);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    for r in &removed {
+        tree.delete(r);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    let mut elements = xs.clone();
+    elements.sort();
+    elements.dedup();
+    elements.retain(|x| !removed.contains(x));
+
+    for (i, element) in elements.iter().enumerate() {
+        assert_eq!(tree.select(i), Some(element));
+    }
+
+    assert_eq!(tree.select(elements.len()), None);
+}
+
+#[quickcheck]
+fn rank_matches_sorted(xs: Vec<u16>, probes: Vec<u16>) {
+    let removed: Vec<u16> = xs.iter().step_by(3).copied().collect();
+
+    let mut tree: RbTree<u16> = RbTree::new();
+
+    for x in &xs {
+        tree.insert(*x);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    for r in &removed {
+        tree.delete(r);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    let mut elements = xs.clone();
+    elements.sort();
+    elements.dedup();
+    elements.retain(|x| !removed.contains(x));
+
+    for probe in probes.iter().chain(elements.iter()) {
+        let expected = elements.iter().filter(|x| *x < probe).count();
+
+        assert_eq!(tree.rank(probe), expected);
+    }
+}
+
+#[quickcheck]
+fn neighbors_match_sorted(xs: Vec<u16>, probes: Vec<u16>) {
+    let mut tree: RbTree<u16> = RbTree::new();
+
+    for x in &xs {
+        tree.insert(*x);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    let mut elements = xs.clone();
+    elements.sort();
+    elements.dedup();
+
+    assert_eq!(tree.first(), elements.first());
+    assert_eq!(tree.last(), elements.last());
+
+    for probe in probes.iter().chain(elements.iter()) {
+        assert_eq!(
+            tree.floor(probe),
+            elements.iter().rev().find(|x| *x <= probe)
+        );
+        assert_eq!(tree.ceiling(probe), elements.iter().find(|x| *x >= probe));
+        assert_eq!(
+            tree.predecessor(probe),
+            elements.iter().rev().find(|x| *x < probe)
+        );
+        assert_eq!(tree.successor(probe), elements.iter().find(|x| *x > probe));
+    }
+}
+
+#[test]
+fn map_neighbors() {
+    let mut map: RbMap<u32, &str> = RbMap::new();
+
+    assert_eq!(map.first(), None);
+    assert_eq!(map.ceiling(&0), None);
+
+    for (key, value) in [(10, "ten"), (30, "thirty"), (20, "twenty")] {
+        map.insert(key, value);
+    }
+
+    assert_eq!(map.first(), Some((&10, &"ten")));
+    assert_eq!(map.last(), Some((&30, &"thirty")));
+    assert_eq!(map.ceiling(&15), Some((&20, &"twenty")));
+    assert_eq!(map.ceiling(&20), Some((&20, &"twenty")));
+    assert_eq!(map.successor(&20), Some((&30, &"thirty")));
+    assert_eq!(map.floor(&29), Some((&20, &"twenty")));
+    assert_eq!(map.predecessor(&10), None);
+    assert_eq!(map.successor(&30), None);
+}
+
+#[test]
+fn map_entry() {
+    let mut map: RbMap<String, u32> = RbMap::new();
+
+    for word in "the quick fox jumps over the lazy dog the end".split_whitespace() {
+        map.entry(word.to_string())
+            .and_modify(|count| *count += 1)
+            .or_insert(1);
+    }
+
+    assert_eq!(map.get("the"), Some(&3));
+    assert_eq!(map.get("fox"), Some(&1));
+    assert_eq!(map.len(), 8);
+
+    *map.entry("cat".to_string()).or_insert_with(|| 10) += 5;
+    assert_eq!(map.get("cat"), Some(&15));
+
+    match map.entry("quick".to_string()) {
+        Entry::Occupied(entry) => assert_eq!(entry.remove(), 1),
+        Entry::Vacant(_) => panic!("quick should be present"),
+    }
+
+    match map.entry("quick".to_string()) {
+        Entry::Occupied(_) => panic!("quick should be removed"),
+        Entry::Vacant(entry) => assert_eq!(*entry.insert(7), 7),
+    }
+
+    assert_eq!(map.get("quick"), Some(&7));
+    assert_eq!(map.len(), 9);
+}
+
+#[quickcheck]
+fn split_off_partitions(xs: Vec<u16>, pivot: u16) {
+    let mut tree: RbTree<u16> = RbTree::new();
+
+    for x in &xs {
+        tree.insert(*x);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    let mut elements = xs.clone();
+    elements.sort();
+    elements.dedup();
+
+    let right = tree.split_off(&pivot);
+
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(right.validate(), Ok(()));
+
+    let (expected_left, expected_right): (Vec<u16>, Vec<u16>) =
+        elements.iter().partition(|x| **x < pivot);
+
+    assert!(tree.iter().eq(expected_left.iter()));
+    assert!(right.iter().eq(expected_right.iter()));
+    assert_eq!(tree.len(), expected_left.len());
+    assert_eq!(right.len(), expected_right.len());
+
+    for (i, element) in expected_right.iter().enumerate() {
+        assert_eq!(right.select(i), Some(element));
+    }
+}
+
+#[quickcheck]
+fn split_off_append_roundtrip(xs: Vec<u16>, pivot: u16) {
+    let mut tree: RbTree<u16> = RbTree::new();
+
+    for x in &xs {
+        tree.insert(*x);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    let expected: Vec<u16> = tree.iter().copied().collect();
+
+    let mut right = tree.split_off(&pivot);
+
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(right.validate(), Ok(()));
+
+    right.append(&mut tree);
+
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(right.validate(), Ok(()));
+
+    assert!(tree.is_empty());
+    assert!(right.iter().eq(expected.iter()));
+
+    if right.insert(pivot) {
+        right.delete(&pivot);
+    }
+
+    for (i, element) in expected.iter().enumerate() {
+        assert_eq!(right.select(i), Some(element));
+    }
+}
+
+#[test]
+fn map_append_overlapping() {
+    let mut first: RbMap<u32, char> = RbMap::new();
+    let mut second: RbMap<u32, char> = RbMap::new();
+
+    for i in 0..50 {
+        first.insert(i * 2, 'a');
+        second.insert(i * 3, 'b');
+    }
+
+    first.append(&mut second);
+
+    assert!(second.is_empty());
+    assert_eq!(first.len(), 50 + 50 - 17);
+    assert_eq!(first.get(&6), Some(&'b'));
+    assert_eq!(first.get(&4), Some(&'a'));
+    assert_eq!(first.get(&9), Some(&'b'));
+}
+
+fn tree_and_set(xs: &[u8]) -> (RbTree<u8>, BTreeSet<u8>) {
+    let mut tree = RbTree::new();
+    let mut set = BTreeSet::new();
+
+    for x in xs {
+        tree.insert(*x);
+        assert_eq!(tree.validate(), Ok(()));
+        set.insert(*x);
+    }
+
+    (tree, set)
+}
+
+#[quickcheck]
+fn set_algebra_iterators(xs: Vec<u8>, ys: Vec<u8>) {
+    let (first, first_set) = tree_and_set(&xs);
+    let (second, second_set) = tree_and_set(&ys);
+
+    assert!(first.union(&second).eq(first_set.union(&second_set)));
+    assert!(first
+        .intersection(&second)
+        .eq(first_set.intersection(&second_set)));
+    assert!(first
+        .difference(&second)
+        .eq(first_set.difference(&second_set)));
+    assert!(first
+        .symmetric_difference(&second)
+        .eq(first_set.symmetric_difference(&second_set)));
+}
+
+#[quickcheck]
+fn set_algebra_in_place(xs: Vec<u8>, ys: Vec<u8>) {
+    let (first_set, second_set) = (tree_and_set(&xs).1, tree_and_set(&ys).1);
+
+    let mut tree = tree_and_set(&xs).0;
+    tree.union_with(tree_and_set(&ys).0);
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.iter().eq(first_set.union(&second_set)));
+    assert_eq!(tree.len(), first_set.union(&second_set).count());
+
+    let mut tree = tree_and_set(&xs).0;
+    tree.intersect_with(tree_and_set(&ys).0);
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.iter().eq(first_set.intersection(&second_set)));
+
+    let mut tree = tree_and_set(&xs).0;
+    tree.difference_with(tree_and_set(&ys).0);
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.iter().eq(first_set.difference(&second_set)));
+
+    let mut tree = tree_and_set(&xs).0;
+    tree.symmetric_difference_with(tree_and_set(&ys).0);
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.iter().eq(first_set.symmetric_difference(&second_set)));
+
+    for (i, key) in tree.iter().enumerate() {
+        assert_eq!(tree.rank(key), i);
+    }
+}
+
+#[quickcheck]
+fn insert_delete_keeps_invariants(ops: Vec<(bool, u8)>) {
+    let mut tree: RbTree<u8> = RbTree::new();
+    let mut set: BTreeSet<u8> = BTreeSet::new();
+
+    for (insert, x) in ops {
+        if insert {
+            assert_eq!(tree.insert(x), set.insert(x));
+        } else {
+            assert_eq!(tree.delete(&x), set.take(&x));
+        }
+
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.len(), set.len());
+    }
+
+    assert!(tree.iter().eq(set.iter()));
+}
+
+#[test]
+fn from_sorted_iter_sizes() {
+    for len in 0..300u32 {
+        let tree = RbTree::from_sorted_iter(0..len);
+
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.len(), len as usize);
+        assert!(tree.iter().copied().eq(0..len));
+    }
+}
+
+#[test]
+fn from_sorted_iter_duplicates() {
+    let map = RbMap::from_sorted_iter(vec![(1, 'a'), (2, 'b'), (2, 'c'), (3, 'd')]);
+
+    assert_eq!(map.validate(), Ok(()));
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(&2), Some(&'c'));
+}
+
+#[test]
+#[should_panic]
+fn from_sorted_iter_unsorted() {
+    RbTree::from_sorted_iter(vec![3, 1, 2]);
+}
+
+#[quickcheck]
+fn collect_and_extend(xs: Vec<u16>, ys: Vec<u16>, sort: bool) {
+    let mut xs = xs;
+    if sort {
+        xs.sort();
+    }
+
+    let mut tree: RbTree<u16> = xs.iter().copied().collect();
+    let mut set: BTreeSet<u16> = xs.iter().copied().collect();
+
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.iter().eq(set.iter()));
+
+    let max = tree.last().copied().unwrap_or(0);
+    let mut ys: Vec<u16> = ys.into_iter().map(|y| y.saturating_add(max)).collect();
+    if sort {
+        ys.sort();
+    }
+
+    tree.extend(ys.iter().copied());
+    set.extend(ys.iter().copied());
+
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(tree.len(), set.len());
+    assert!(tree.iter().eq(set.iter()));
+}
+
+#[test]
+fn clone_is_independent() {
+    let mut tree: RbTree<String> = ["b", "a", "c"].iter().map(|s| s.to_string()).collect();
+    let copy = tree.clone();
+
+    tree.insert("d".to_string());
+    tree.delete("a");
+
+    assert_eq!(copy.validate(), Ok(()));
+    assert_eq!(copy.inorder_traverse(), vec!["a", "b", "c"]);
+    assert_eq!(tree.inorder_traverse(), vec!["b", "c", "d"]);
+}
+
+#[quickcheck]
+fn comparisons_match_btreeset(xs: Vec<u8>, ys: Vec<u8>) {
+    let (first, first_set) = tree_and_set(&xs);
+    let (second, second_set) = tree_and_set(&ys);
+
+    assert_eq!(first == second, first_set == second_set);
+    assert_eq!(first.cmp(&second), first_set.cmp(&second_set));
+    assert_eq!(first, first.clone());
+}
+
+#[test]
+fn hash_and_default() {
+    let mut trees: HashSet<RbTree<u32>> = HashSet::new();
+
+    trees.insert([3, 1, 2].into_iter().collect());
+    trees.insert([1, 2, 3].into_iter().collect());
+    trees.insert(RbTree::default());
+
+    assert_eq!(trees.len(), 2);
+    assert!(trees.contains(&RbTree::new()));
+}
+
+#[test]
+fn debug_format() {
+    let tree: RbTree<u32> = [3, 1, 2].into_iter().collect();
+    let map: RbMap<u32, char> = [(2, 'b'), (1, 'a')].into_iter().collect();
+
+    assert_eq!(format!("{:?}", tree), "{1, 2, 3}");
+    assert_eq!(format!("{:?}", map), "{1: 'a', 2: 'b'}");
+}
+
+#[test]
+fn drop_frees_every_key() {
+    let counter = Rc::new(());
+
+    let mut tree: RbTree<(u32, Rc<()>)> = RbTree::new();
+    for i in 0..100 {
+        tree.insert((i, Rc::clone(&counter)));
+    }
+
+    let right = tree.split_off(&(50, Rc::clone(&counter)));
+    let copy = right.clone();
+
+    assert_eq!(Rc::strong_count(&counter), 151);
+
+    drop(tree);
+    drop(right);
+    drop(copy);
+
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn thread_safety() {
+    assert_send_sync::<RbTree<String>>();
+    assert_send_sync::<RbMap<u32, Vec<u8>>>();
+    assert_send_sync::<nasp_homework::rbtree::Iter<'static, u32>>();
+    assert_send_sync::<ConcurrentRbTree<u32>>();
+    assert_send_sync::<nasp_homework::rbtree::Cursor<'static, u32>>();
+    assert_send_sync::<nasp_homework::rbtree::CursorMut<'static, u32>>();
+    assert_send_sync::<nasp_homework::rbtree::ExtractIf<'static, u32, fn(&u32) -> bool>>();
+
+    let tree: RbTree<u32> = (0..100).collect();
+    let handle = thread::spawn(move || tree.len());
+
+    assert_eq!(handle.join().unwrap(), 100);
+}
+
+#[test]
+fn concurrent_tree() {
+    let tree: Arc<ConcurrentRbTree<u32>> = Arc::new(ConcurrentRbTree::new());
+
+    let writers: Vec<_> = (0..4)
+        .map(|t| {
+            let tree = Arc::clone(&tree);
+            thread::spawn(move || {
+                for i in 0..250 {
+                    tree.insert(i * 4 + t);
+                }
+            })
+        })
+        .collect();
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let tree = Arc::clone(&tree);
+            thread::spawn(move || {
+                for _ in 0..50 {
+                    let guard = tree.read();
+                    assert_eq!(guard.validate(), Ok(()));
+                    assert_eq!(guard.iter().count(), guard.len());
+                }
+            })
+        })
+        .collect();
+
+    for handle in writers.into_iter().chain(readers) {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(tree.len(), 1000);
+    assert!(tree.contains(&999));
+    assert_eq!(tree.delete(&999), Some(999));
+
+    let snapshot = tree.snapshot();
+    tree.insert(5000);
+
+    assert_eq!(snapshot.len(), 999);
+    assert!(Arc::try_unwrap(tree)
+        .ok()
+        .unwrap()
+        .into_inner()
+        .iter()
+        .eq(snapshot.iter().chain([5000].iter())));
+}
+
+#[quickcheck]
+fn persistent_versions(ops: Vec<(bool, u8)>) {
+    let mut versions = vec![PersistentRbTree::new()];
+    let mut snapshots = vec![BTreeSet::new()];
+
+    for (insert, x) in ops {
+        let tree = versions.last().unwrap();
+        let mut set = snapshots.last().unwrap().clone();
+
+        let next = if insert {
+            set.insert(x);
+            tree.insert(x)
+        } else {
+            set.remove(&x);
+            tree.delete(&x)
+        };
+
+        assert_eq!(next.validate(), Ok(()));
+
+        versions.push(next);
+        snapshots.push(set);
+    }
+
+    for (tree, set) in versions.iter().zip(&snapshots) {
+        assert_eq!(tree.len(), set.len());
+        assert!(tree.iter().eq(set.iter()));
+    }
+}
+
+#[test]
+fn persistent_shares_structure() {
+    let tree: PersistentRbTree<u32> = (0..1000).collect();
+
+    let same = tree.insert(500);
+    assert!(same.shares_root_with(&tree));
+
+    let inserted = tree.insert(1000);
+    let deleted = tree.delete(&0);
+
+    assert!(!inserted.shares_root_with(&tree));
+    assert_eq!(tree.len(), 1000);
+    assert_eq!(inserted.len(), 1001);
+    assert_eq!(deleted.len(), 999);
+    assert!(tree.contains(&0) && !tree.contains(&1000));
+    assert!(inserted.contains(&1000) && !deleted.contains(&0));
+    assert_ne!(tree, inserted);
+    assert_eq!(inserted.delete(&1000), tree);
+
+    assert_send_sync::<PersistentRbTree<u32>>();
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct SumMax {
+    sum: u64,
+    max: u32,
+}
+
+impl Augment<u32> for SumMax {
+    fn from_key(key: &u32) -> Self {
+        SumMax {
+            sum: *key as u64,
+            max: *key,
+        }
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        SumMax {
+            sum: self.sum + other.sum,
+            max: self.max.max(other.max),
+        }
+    }
+}
+
+fn sum_max<'a>(keys: impl Iterator<Item = &'a u32>) -> Option<SumMax> {
+    keys.map(SumMax::from_key).reduce(|a, b| a.combine(&b))
+}
+
+#[quickcheck]
+fn range_aggregate_matches_filter(xs: Vec<u32>, ops: Vec<(bool, u32)>, a: u32, b: u32) {
+    let mut tree: RbTree<u32, SumMax> = xs.iter().copied().collect();
+    let mut set: BTreeSet<u32> = xs.into_iter().collect();
+
+    for (insert, x) in ops {
+        if insert {
+            tree.insert(x);
+            set.insert(x);
+        } else {
+            tree.delete(&x);
+            set.remove(&x);
+        }
+    }
+
+    let (lo, hi) = (a.min(b), a.max(b));
+
+    assert_eq!(tree.range_aggregate(..), sum_max(set.iter()));
+    assert_eq!(tree.range_aggregate(lo..hi), sum_max(set.range(lo..hi)));
+    assert_eq!(tree.range_aggregate(lo..=hi), sum_max(set.range(lo..=hi)));
+    assert_eq!(tree.range_aggregate(lo..), sum_max(set.range(lo..)));
+
+    let right = tree.split_off(&lo);
+
+    assert_eq!(tree.range_aggregate(..), sum_max(set.range(..lo)));
+    assert_eq!(right.range_aggregate(..), sum_max(set.range(lo..)));
+}
+
+#[quickcheck]
+fn interval_tree_matches_scan(intervals: Vec<(u8, u8)>, removed: Vec<(u8, u8)>, a: u8, b: u8) {
+    let normalize = |(x, y): (u8, u8)| (x.min(y), x.max(y));
+
+    let mut tree: IntervalTree<u8> = intervals.iter().copied().map(normalize).collect();
+    let mut set: BTreeSet<(u8, u8)> = intervals.into_iter().map(normalize).collect();
+
+    for (start, end) in removed.into_iter().map(normalize) {
+        assert_eq!(tree.remove(start, end), set.remove(&(start, end)));
+    }
+
+    assert_eq!(tree.len(), set.len());
+    assert_eq!(tree.max_end(), set.iter().map(|(_, end)| end).max());
+
+    let (lo, hi) = normalize((a, b));
+
+    let found: Vec<(u8, u8)> = tree
+        .overlapping_range(lo, hi)
+        .map(|interval| (interval.start, interval.end))
+        .collect();
+    let expected: Vec<(u8, u8)> = set
+        .iter()
+        .copied()
+        .filter(|&(start, end)| start <= hi && lo <= end)
+        .collect();
+    assert_eq!(found, expected);
+
+    let found: Vec<(u8, u8)> = tree
+        .overlapping(a)
+        .map(|interval| (interval.start, interval.end))
+        .collect();
+    let expected: Vec<(u8, u8)> = set
+        .iter()
+        .copied()
+        .filter(|&(start, end)| start <= a && a <= end)
+        .collect();
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn interval_tree_points() {
+    let mut tree = IntervalTree::new();
+    tree.insert(15, 20);
+    tree.insert(10, 30);
+    tree.insert(17, 19);
+    tree.insert(5, 20);
+    tree.insert(12, 15);
+    tree.insert(30, 40);
+
+    let at = |point| {
+        tree.overlapping(point)
+            .map(|interval| (interval.start, interval.end))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(at(4), vec![]);
+    assert_eq!(at(14), vec![(5, 20), (10, 30), (12, 15)]);
+    assert_eq!(at(30), vec![(10, 30), (30, 40)]);
+    assert_eq!(at(41), vec![]);
+    assert!(tree.contains(17, 19));
+    assert!(!tree.insert(17, 19));
+}
+
+#[test]
+fn interval_tree_reversed_bounds() {
+    let mut tree: IntervalTree<u32> = [(3, 5), (1, 9)].into_iter().collect();
+
+    assert!(!tree.contains(5, 3));
+    assert!(!tree.remove(9, 1));
+    assert_eq!(tree.overlapping_range(5, 3).count(), 0);
+    assert_eq!(tree.len(), 2);
+
+    assert!(!tree.insert(7, 2));
+    tree.extend([(8, 4), (2, 6)]);
+
+    assert!(tree
+        .iter()
+        .map(|i| (i.start, i.end))
+        .eq([(1, 9), (2, 6), (3, 5)]));
+}
+
+#[quickcheck]
+fn descending_comparator(xs: Vec<u8>, ys: Vec<u8>, pivot: u8) {
+    let descending = FnComparator(|a: &u8, b: &u8| b.cmp(a));
+
+    let mut tree = RbTree::with_comparator(descending);
+    let mut set: BTreeSet<Reverse<u8>> = BTreeSet::new();
+
+    for &x in &xs {
+        assert_eq!(tree.insert(x), set.insert(Reverse(x)));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+    for y in &ys {
+        assert_eq!(tree.delete(y), set.take(&Reverse(*y)).map(|Reverse(y)| y));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    assert!(tree.iter().eq(set.iter().map(|Reverse(x)| x)));
+    assert_eq!(tree.first(), set.first().map(|Reverse(x)| x));
+    assert_eq!(tree.rank(&pivot), set.range(..Reverse(pivot)).count());
+    assert!(tree
+        .range(pivot..)
+        .eq(set.range(Reverse(pivot)..).map(|Reverse(x)| x)));
+
+    let mut other = RbTree::with_comparator(descending);
+    other.extend(ys);
+    let union: Vec<u8> = tree.union(&other).copied().collect();
+
+    tree.union_with(other.clone());
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.iter().eq(union.iter()));
+
+    let right = tree.split_off(&pivot);
+    assert!(tree.iter().all(|&x| x > pivot));
+    assert!(right.iter().all(|&x| x <= pivot));
+}
+
+#[test]
+fn custom_comparators() {
+    let mut names = RbTree::with_comparator(FnComparator(|a: &String, b: &String| {
+        a.to_lowercase().cmp(&b.to_lowercase())
+    }));
+    assert!(names.insert("bob".to_string()));
+    assert!(names.insert("Alice".to_string()));
+    assert!(!names.insert("ALICE".to_string()));
+    assert!(names.contains(&"alice".to_string()));
+    assert_eq!(names.delete(&"BOB".to_string()), Some("bob".to_string()));
+    assert!(names.iter().eq(["Alice"].iter()));
+
+    struct Person {
+        name: &'static str,
+        age: u32,
+    }
+
+    let mut people =
+        RbMap::with_comparator(FnComparator(|a: &Person, b: &Person| a.age.cmp(&b.age)));
+    people.insert(
+        Person {
+            name: "Ann",
+            age: 40,
+        },
+        (),
+    );
+    people.insert(
+        Person {
+            name: "Ben",
+            age: 25,
+        },
+        (),
+    );
+    people.insert(
+        Person {
+            name: "Cid",
+            age: 33,
+        },
+        (),
+    );
+    let order: Vec<&str> = people.iter().map(|(person, _)| person.name).collect();
+    assert_eq!(order, ["Ben", "Cid", "Ann"]);
+
+    let boxed: Box<dyn Comparator<i32>> = Box::new(FnComparator(|a: &i32, b: &i32| b.cmp(a)));
+    let mut tree = RbTree::with_comparator(boxed);
+    for x in [1, 5, 3] {
+        tree.insert(x);
+    }
+    assert!(tree.iter().eq([5, 3, 1].iter()));
+
+    let mut tree = RbTree::with_comparator(Box::new(Natural));
+    tree.extend([2, 0, 1]);
+    assert!(tree.iter().eq([0, 1, 2].iter()));
+
+    let shared: Arc<dyn Comparator<i32> + Send + Sync> =
+        Arc::new(FnComparator(|a: &i32, b: &i32| {
+            a.abs().cmp(&b.abs()).then(a.cmp(b))
+        }));
+    let mut tree = RbTree::with_comparator(shared);
+    tree.extend([-3, 2, -1, 3]);
+    let right = tree.split_off(&2);
+    assert!(tree.iter().eq([-1].iter()));
+    assert!(right.iter().eq([2, -3, 3].iter()));
+    assert_eq!(right.first().map(|x| x.cmp(&2)), Some(Ordering::Equal));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "not sorted by this map's comparator")]
+fn merge_rejects_mismatched_comparators() {
+    let ascending: Arc<dyn Comparator<i32>> = Arc::new(Natural);
+    let descending: Arc<dyn Comparator<i32>> = Arc::new(FnComparator(|a: &i32, b: &i32| b.cmp(a)));
+
+    let mut tree = RbTree::with_comparator(ascending);
+    tree.extend([1, 2]);
+    let mut other = RbTree::with_comparator(descending);
+    other.extend([3, 4]);
+
+    tree.union_with(other);
+}
+
+#[quickcheck]
+fn multiset_matches_counts(ops: Vec<(u8, u8)>) -> bool {
+    let mut set = RbMultiSet::new();
+    let mut counts = BTreeMap::new();
+
+    for (op, key) in ops {
+        let key = key % 16;
+        match op % 4 {
+            0 | 1 => {
+                let count = counts.entry(key).or_insert(0);
+                *count += 1;
+                if set.insert(key) != *count {
+                    return false;
+                }
+            }
+            2 => {
+                let expected = match counts.get_mut(&key) {
+                    Some(count) if *count > 1 => {
+                        *count -= 1;
+                        true
+                    }
+                    Some(_) => counts.remove(&key).is_some(),
+                    None => false,
+                };
+                if set.remove_one(&key) != expected {
+                    return false;
+                }
+            }
+            _ => {
+                if set.remove_all(&key) != counts.remove(&key).unwrap_or(0) {
+                    return false;
+                }
+            }
+        }
+    }
+
+    let expected: Vec<u8> = counts
+        .iter()
+        .flat_map(|(&key, &count)| std::iter::repeat_n(key, count))
+        .collect();
+
+    set.len() == expected.len()
+        && set.iter().len() == expected.len()
+        && set.distinct_len() == counts.len()
+        && set.iter().copied().eq(expected)
+        && (0..16).all(|key| set.count(&key) == counts.get(&key).copied().unwrap_or(0))
+}
+
+#[test]
+fn multiset_duplicates() {
+    let mut timestamps: RbMultiSet<u64> = [30, 10, 20, 10, 30, 30].into_iter().collect();
+
+    assert_eq!(timestamps.len(), 6);
+    assert_eq!(timestamps.distinct_len(), 3);
+    assert_eq!(timestamps.count(&30), 3);
+    assert_eq!(timestamps.count(&40), 0);
+    assert!(timestamps.iter().eq([10, 10, 20, 30, 30, 30].iter()));
+    assert_eq!(timestamps.first(), Some((&10, 2)));
+    assert_eq!(timestamps.last(), Some((&30, 3)));
+
+    assert!(timestamps.remove_one(&10));
+    assert!(timestamps.remove_one(&10));
+    assert!(!timestamps.remove_one(&10));
+    assert!(!timestamps.contains(&10));
+    assert_eq!(timestamps.remove_all(&30), 3);
+    assert_eq!(timestamps.remove_all(&30), 0);
+    assert!(timestamps.iter().eq([20].iter()));
+    assert_eq!(format!("{timestamps:?}"), "[20]");
+
+    timestamps.clear();
+    assert!(timestamps.is_empty());
+}
+
+#[quickcheck]
+fn arena_matches_pointer_tree(ops: Vec<(bool, u8)>) {
+    let mut arena = ArenaRbTree::new();
+    let mut tree = RbTree::new();
+
+    for (insert, x) in ops {
+        let x = x % 64;
+        if insert {
+            assert_eq!(arena.insert(x), tree.insert(x));
+        } else {
+            assert_eq!(arena.delete(&x), tree.delete(&x));
+        }
+
+        assert_eq!(arena.validate(), Ok(()));
+        assert_eq!(arena.len(), tree.len());
+    }
+
+    assert!(arena.iter().eq(tree.iter()));
+    assert!(arena.iter().rev().eq(tree.iter().rev()));
+    assert_eq!(arena.inorder_traverse(), tree.inorder_traverse());
+    assert_eq!(arena.preorder_tranverse(), tree.preorder_tranverse());
+    assert_eq!(arena.postorder_traverse(), tree.postorder_traverse());
+    assert_eq!(arena.level_order_traverse(), tree.level_order_traverse());
+    assert_eq!(arena.first(), tree.first());
+    assert_eq!(arena.last(), tree.last());
+
+    for x in 0..=u8::MAX {
+        assert_eq!(arena.contains(&x), tree.contains(&x));
+        assert_eq!(arena.floor(&x), tree.floor(&x));
+        assert_eq!(arena.ceiling(&x), tree.ceiling(&x));
+        assert_eq!(arena.predecessor(&x), tree.predecessor(&x));
+        assert_eq!(arena.successor(&x), tree.successor(&x));
+        assert_eq!(arena.rank(&x), tree.rank(&x));
+        assert_eq!(arena.select(x as usize), tree.select(x as usize));
+    }
+}
+
+#[test]
+fn arena_reuses_slots() {
+    let mut tree: ArenaRbTree<u32> = ArenaRbTree::with_capacity(100);
+
+    for round in 0..10 {
+        tree.extend(0..100);
+        for x in (0..100).filter(|x| x % 3 != round % 3) {
+            assert_eq!(tree.delete(&x), Some(x));
+        }
+        assert_eq!(tree.validate(), Ok(()));
+        tree.clear();
+    }
+
+    assert_eq!(tree.capacity(), 100);
+
+    let tree: ArenaRbTree<u32> = (0..100).rev().collect();
+    let cloned = tree.clone();
+    assert_eq!(cloned, tree);
+    assert_eq!(format!("{:?}", tree.select(42)), "Some(42)");
+}
+
+#[quickcheck]
+fn arena_bulk_operations_match_pointer_tree(xs: Vec<u8>, ys: Vec<u8>, pivot: u8) {
+    let mut sorted = xs.clone();
+    sorted.sort();
+
+    let arena = ArenaRbTree::from_sorted_iter(sorted.iter().copied());
+    let tree = RbTree::from_sorted_iter(sorted.iter().copied());
+    assert_eq!(arena.validate(), Ok(()));
+    assert_eq!(arena.to_dot(), tree.to_dot());
+    assert_eq!(arena.to_pretty_string(), tree.to_pretty_string());
+
+    let other_arena: ArenaRbTree<u8> = ys.iter().copied().collect();
+    let other_tree: RbTree<u8> = ys.iter().copied().collect();
+
+    assert!(arena.range(pivot..).eq(tree.range(pivot..)));
+    assert!(arena.range(..=pivot).rev().eq(tree.range(..=pivot).rev()));
+    assert!(arena.union(&other_arena).eq(tree.union(&other_tree)));
+    assert!(arena
+        .intersection(&other_arena)
+        .eq(tree.intersection(&other_tree)));
+    assert!(arena
+        .difference(&other_arena)
+        .eq(tree.difference(&other_tree)));
+    assert!(arena
+        .symmetric_difference(&other_arena)
+        .eq(tree.symmetric_difference(&other_tree)));
+
+    let mut arenas = [arena.clone(), arena.clone(), arena.clone(), arena.clone()];
+    let mut trees = [tree.clone(), tree.clone(), tree.clone(), tree.clone()];
+    arenas[0].union_with(other_arena.clone());
+    trees[0].union_with(other_tree.clone());
+    arenas[1].intersect_with(other_arena.clone());
+    trees[1].intersect_with(other_tree.clone());
+    arenas[2].difference_with(other_arena.clone());
+    trees[2].difference_with(other_tree.clone());
+    arenas[3].symmetric_difference_with(other_arena.clone());
+    trees[3].symmetric_difference_with(other_tree.clone());
+    for (arena, tree) in arenas.iter().zip(&trees) {
+        assert_eq!(arena.validate(), Ok(()));
+        assert!(arena.iter().eq(tree.iter()));
+    }
+
+    let mut arena = arena;
+    let mut tree = tree;
+    let mut right_arena = arena.split_off(&pivot);
+    let mut right_tree = tree.split_off(&pivot);
+    assert_eq!(arena.validate(), Ok(()));
+    assert_eq!(right_arena.validate(), Ok(()));
+    assert!(arena.iter().eq(tree.iter()));
+    assert!(right_arena.iter().eq(right_tree.iter()));
+
+    arena.append(&mut right_arena);
+    tree.append(&mut right_tree);
+    assert!(right_arena.is_empty());
+    assert!(arena.iter().eq(tree.iter()));
+
+    arena.retain(|x| x % 3 != 0);
+    tree.retain(|x| x % 3 != 0);
+    assert_eq!(arena.validate(), Ok(()));
+    assert!(arena.iter().eq(tree.iter()));
+
+    let extracted: Vec<u8> = arena.extract_if(|x| x % 2 == 0).collect();
+    assert!(extracted.into_iter().eq(tree.extract_if(|x| x % 2 == 0)));
+    assert_eq!(arena.validate(), Ok(()));
+    assert!(arena.iter().eq(tree.iter()));
+}
+
+#[quickcheck]
+fn arena_small_merges_match_pointer_tree(ys: Vec<u16>) {
+    let ys: Vec<u16> = ys.into_iter().take(8).map(|y| y % 3000).collect();
+
+    let arena: ArenaRbTree<u16> = (0..1000).map(|x| x * 3).collect();
+    let tree: RbTree<u16> = (0..1000).map(|x| x * 3).collect();
+    let other_arena: ArenaRbTree<u16> = ys.iter().copied().collect();
+    let other_tree: RbTree<u16> = ys.iter().copied().collect();
+
+    let mut arenas = [arena.clone(), arena.clone(), arena.clone(), arena.clone()];
+    let mut trees = [tree.clone(), tree.clone(), tree.clone(), tree.clone()];
+    arenas[0].union_with(other_arena.clone());
+    trees[0].union_with(other_tree.clone());
+    arenas[1].intersect_with(other_arena.clone());
+    trees[1].intersect_with(other_tree.clone());
+    arenas[2].difference_with(other_arena.clone());
+    trees[2].difference_with(other_tree.clone());
+    arenas[3].symmetric_difference_with(other_arena);
+    trees[3].symmetric_difference_with(other_tree);
+    for (arena, tree) in arenas.iter().zip(&trees) {
+        assert_eq!(arena.validate(), Ok(()));
+        assert!(arena.iter().eq(tree.iter()));
+    }
+
+    let mut sorted = ys.clone();
+    sorted.sort();
+    let mut arenas = [arena.clone(), arena];
+    let mut trees = [tree.clone(), tree];
+    arenas[0].extend(sorted.iter().copied());
+    trees[0].extend(sorted.iter().copied());
+    arenas[1].extend(ys.iter().copied());
+    trees[1].extend(ys.iter().copied());
+    for (arena, tree) in arenas.iter().zip(&trees) {
+        assert_eq!(arena.validate(), Ok(()));
+        assert!(arena.iter().eq(tree.iter()));
+    }
+}
+
+#[test]
+fn arena_cursors() {
+    let mut tree: ArenaRbTree<u32> = (0..20).map(|x| x * 5).collect();
+
+    let mut cursor = tree.lower_bound(Bound::Included(&12));
+    assert_eq!(cursor.key(), Some(&15));
+    cursor.move_prev();
+    assert_eq!(cursor.key(), Some(&10));
+    assert_eq!(cursor.peek_next(), Some(&15));
+    assert_eq!(tree.upper_bound(Bound::Excluded(&0)).key(), None);
+    assert_eq!(tree.upper_bound(Bound::Excluded(&0)).peek_next(), Some(&0));
+
+    let mut cursor = tree.lower_bound_mut(Bound::Included(&50));
+    cursor.insert_before(48).unwrap();
+    cursor.insert_after(52).unwrap();
+    assert_eq!(cursor.peek_prev(), Some(&48));
+    assert_eq!(cursor.remove_current(), Some(50));
+    assert_eq!(cursor.key(), Some(&52));
+
+    while cursor.remove_current().is_some() {}
+    assert_eq!(cursor.key(), None);
+    cursor.insert_before(200).unwrap();
+
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree
+        .iter()
+        .copied()
+        .eq((0..10).map(|x| x * 5).chain([48, 200])));
+}
+
+#[test]
+fn traversal_orders() {
+    let mut tree = RbTree::new();
+    for x in 1..=7 {
+        tree.insert(x);
+    }
+
+    assert_eq!(tree.inorder_traverse(), [&1, &2, &3, &4, &5, &6, &7]);
+    assert_eq!(tree.preorder_tranverse(), [&2, &1, &4, &3, &6, &5, &7]);
+    assert_eq!(tree.postorder_traverse(), [&1, &3, &5, &7, &6, &4, &2]);
+    assert_eq!(
+        tree.level_order_traverse(),
+        [vec![&2], vec![&1, &4], vec![&3, &6], vec![&5, &7]]
+    );
+
+    let empty: RbTree<u32> = RbTree::new();
+    assert!(empty.postorder_traverse().is_empty());
+    assert!(empty.level_order_traverse().is_empty());
+}
+
+#[quickcheck]
+fn level_order_groups_by_depth(elements: Vec<u16>) {
+    let tree: RbTree<u16> = elements.into_iter().collect();
+    let levels = tree.level_order_traverse();
+
+    let mut flattened: Vec<&u16> = levels.concat();
+    flattened.sort();
+    assert_eq!(flattened, tree.inorder_traverse());
+
+    let height_bound = 2 * (usize::BITS - tree.len().leading_zeros()) as usize;
+    assert!(levels.len() <= height_bound);
+
+    for (depth, level) in levels.iter().enumerate() {
+        assert!(!level.is_empty());
+        assert!(level.len() <= 1 << depth);
+        assert!(level.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    if let Some(root) = levels.first() {
+        assert_eq!(tree.preorder_tranverse().first(), Some(&root[0]));
+        assert_eq!(tree.postorder_traverse().last(), Some(&root[0]));
+    }
+}
+
+#[test]
+fn large_tree_traversal_and_drop() {
+    let len = 1_000_000;
+    let tree = RbTree::from_sorted_iter(0..len);
+
+    assert_eq!(tree.inorder_traverse().len(), len);
+    assert_eq!(tree.postorder_traverse().first(), Some(&&0));
+    assert_eq!(tree.level_order_traverse().concat().len(), len);
+
+    drop(tree);
+}
+
+#[test]
+fn pretty_string_and_dot() {
+    let mut tree = RbTree::new();
+    for x in 1..=5 {
+        tree.insert(x);
+    }
+
+    assert_eq!(
+        tree.to_pretty_string().lines().collect::<Vec<_>>(),
+        [
+            "    ┌── 5 [R]",
+            "┌── 4 [B]",
+            "│   └── 3 [R]",
+            "2 [B]",
+            "└── 1 [B]",
+        ]
+    );
+
+    let dot = tree.to_dot();
+    assert!(dot.starts_with("digraph RbTree {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains("n0 [label=\"2\", fillcolor=black];"));
+    assert_eq!(dot.matches("fillcolor=red").count(), 2);
+    assert_eq!(dot.matches(" -> n").count(), tree.len() - 1);
+    assert_eq!(dot.matches("[shape=point]").count(), 0);
+
+    tree.delete(&5);
+    assert_eq!(tree.to_dot().matches("[shape=point]").count(), 1);
+
+    let empty: RbTree<u32> = RbTree::new();
+    assert_eq!(empty.to_pretty_string(), "");
+
+    let mut quoted = RbTree::new();
+    quoted.insert("say \"hi\"");
+    assert!(quoted.to_dot().contains(r#"[label="\"say \\\"hi\\\"\"""#));
+}
+
+#[quickcheck]
+fn retain_matches_btreeset(elements: Vec<u16>, modulus: u8) {
+    let modulus = u16::from(modulus.max(1));
+    let mut tree: RbTree<u16> = elements.iter().copied().collect();
+    let mut set: BTreeSet<u16> = elements.into_iter().collect();
+
+    let mut visited = Vec::new();
+    tree.retain(|&x| {
+        visited.push(x);
+        x % modulus != 0
+    });
+    set.retain(|&x| x % modulus != 0);
+
+    assert!(visited.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(tree.len(), set.len());
+    assert!(tree.iter().eq(set.iter()));
+}
+
+#[quickcheck]
+fn extract_if_is_lazy(elements: Vec<u16>, take: usize) {
+    let mut tree: RbTree<u16> = elements.iter().copied().collect();
+    let mut set: BTreeSet<u16> = elements.into_iter().collect();
+    let evens: Vec<u16> = set.iter().copied().filter(|x| x % 2 == 0).collect();
+    let take = take % (evens.len() + 1);
+
+    let extracted: Vec<u16> = tree.extract_if(|x| x % 2 == 0).take(take).collect();
+    for x in &extracted {
+        set.remove(x);
+    }
+
+    assert_eq!(extracted, evens[..take]);
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.iter().eq(set.iter()));
+}
+
+#[test]
+fn map_retain_and_extract_if() {
+    let mut map: RbMap<u32, u32> = (0..100).map(|x| (x, x)).collect();
+
+    map.retain(|&key, value| {
+        *value *= 10;
+        key % 3 != 0
+    });
+    assert_eq!(map.validate(), Ok(()));
+    assert_eq!(map.len(), 66);
+    assert!(map
+        .iter()
+        .all(|(&key, &value)| key % 3 != 0 && value == key * 10));
+
+    let extracted: Vec<(u32, u32)> = map.extract_if(|&key, _| key >= 90).collect();
+    assert_eq!(
+        extracted,
+        [
+            (91, 910),
+            (92, 920),
+            (94, 940),
+            (95, 950),
+            (97, 970),
+            (98, 980)
+        ]
+    );
+    assert_eq!(map.validate(), Ok(()));
+    assert_eq!(map.last(), Some((&89, &890)));
+
+    map.retain(|_, _| false);
+    assert!(map.is_empty());
+    assert_eq!(map.extract_if(|_, _| true).next(), None);
+}
+
+#[quickcheck]
+fn cursor_walks_like_range(elements: Vec<u8>, pivot: u8) {
+    let tree: RbTree<u8> = elements.iter().copied().collect();
+    let set: BTreeSet<u8> = elements.into_iter().collect();
+
+    let mut cursor = tree.lower_bound(Bound::Included(&pivot));
+    let mut forward = Vec::new();
+    while let Some(&key) = cursor.key() {
+        forward.push(key);
+        cursor.move_next();
+    }
+    assert!(forward.iter().eq(set.range(pivot..)));
+
+    let mut cursor = tree.upper_bound(Bound::Excluded(&pivot));
+    let mut backward = Vec::new();
+    while let Some(&key) = cursor.key() {
+        backward.push(key);
+        cursor.move_prev();
+    }
+    assert!(backward.iter().eq(set.range(..pivot).rev()));
+}
+
+#[test]
+fn cursor_ghost_and_peek() {
+    let tree: RbTree<u32> = (0..10).map(|x| x * 2).collect();
+
+    let mut cursor = tree.lower_bound(Bound::Excluded(&5));
+    assert_eq!(cursor.key(), Some(&6));
+    assert_eq!(cursor.peek_prev(), Some(&4));
+    assert_eq!(cursor.peek_next(), Some(&8));
+    cursor.move_prev();
+    cursor.move_prev();
+    assert_eq!(cursor.key(), Some(&2));
+
+    let mut ghost = tree.upper_bound(Bound::Excluded(&0));
+    assert_eq!(ghost.key(), None);
+    assert_eq!(ghost.peek_next(), Some(&0));
+    assert_eq!(ghost.peek_prev(), Some(&18));
+    ghost.move_prev();
+    assert_eq!(ghost.key(), Some(&18));
+    ghost.move_next();
+    assert_eq!(ghost.key(), None);
+
+    let empty: RbTree<u32> = RbTree::new();
+    let mut cursor = empty.lower_bound::<u32>(Bound::Unbounded);
+    cursor.move_next();
+    assert_eq!(cursor.key(), None);
+}
+
+#[test]
+fn cursor_mut_insert_and_remove() {
+    let mut tree: RbTree<u32> = (0..10).map(|x| x * 2).collect();
+
+    let mut cursor = tree.lower_bound_mut::<u32>(Bound::Unbounded);
+    while let Some(&key) = cursor.key() {
+        cursor.insert_after(key + 1).unwrap();
+        cursor.move_next();
+        cursor.move_next();
+    }
+    cursor.insert_before(100).unwrap();
+    cursor.insert_before(101).unwrap();
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.iter().copied().eq((0..20).chain([100, 101])));
+
+    let mut cursor = tree.lower_bound_mut(Bound::Included(&5));
+    while let Some(&key) = cursor.key() {
+        if key % 4 == 1 {
+            assert_eq!(cursor.remove_current(), Some(key));
+        } else {
+            cursor.move_next();
+        }
+    }
+    assert_eq!(cursor.remove_current(), None);
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree
+        .iter()
+        .copied()
+        .eq((0..20).chain([100, 101]).filter(|x| *x < 5 || x % 4 != 1)));
+
+    let mut empty = RbTree::new();
+    let mut cursor = empty.upper_bound_mut::<u32>(Bound::Unbounded);
+    assert_eq!(cursor.key(), None);
+    cursor.insert_before(1).unwrap();
+    cursor.insert_after(0).unwrap();
+    assert_eq!(cursor.peek_next(), Some(&0));
+    assert!(empty.iter().eq([0, 1].iter()));
+}
+
+#[test]
+fn cursor_mut_insert_out_of_order() {
+    let mut tree: RbTree<u32> = [1, 3].into_iter().collect();
+
+    let mut cursor = tree.lower_bound_mut(Bound::Included(&1));
+    assert_eq!(cursor.insert_after(3), Err(UnorderedKeyError));
+    assert_eq!(cursor.insert_after(1), Err(UnorderedKeyError));
+    assert_eq!(cursor.insert_before(2), Err(UnorderedKeyError));
+    assert_eq!(cursor.insert_after(2), Ok(()));
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.iter().eq([1, 2, 3].iter()));
+
+    let mut arena: ArenaRbTree<u32> = [1, 3].into_iter().collect();
+    let mut cursor = arena.lower_bound_mut(Bound::Included(&3));
+    assert_eq!(cursor.insert_before(1), Err(UnorderedKeyError));
+    assert_eq!(cursor.insert_after(0), Err(UnorderedKeyError));
+    assert_eq!(cursor.insert_before(2), Ok(()));
+    assert_eq!(arena.validate(), Ok(()));
+    assert!(arena.iter().eq([1, 2, 3].iter()));
 }
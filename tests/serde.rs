@@ -0,0 +1,88 @@
+#![cfg(feature = "serde")]
+
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use nasp_homework::{
+    fibheap::FibHeap,
+    rbtree::{RbMap, RbTree},
+};
+use quickcheck_macros::*;
+use serde_test::{assert_tokens, Token};
+
+#[quickcheck]
+fn tree_round_trip(xs: Vec<i32>) {
+    let tree: RbTree<i32> = xs.into_iter().collect();
+
+    let json = serde_json::to_string(&tree).unwrap();
+    let restored: RbTree<i32> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(restored.validate(), Ok(()));
+    assert_eq!(restored, tree);
+    assert_eq!(
+        json,
+        serde_json::to_string(&tree.inorder_traverse()).unwrap()
+    );
+}
+
+#[quickcheck]
+fn map_round_trip(entries: Vec<(u16, String)>) {
+    let map: RbMap<u16, String> = entries.iter().cloned().collect();
+    let expected: BTreeMap<u16, String> = entries.into_iter().collect();
+
+    let json = serde_json::to_string(&map).unwrap();
+    let restored: RbMap<u16, String> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(restored.validate(), Ok(()));
+    assert_eq!(restored, map);
+    assert_eq!(json, serde_json::to_string(&expected).unwrap());
+}
+
+#[quickcheck]
+fn heap_round_trip(xs: Vec<i64>) {
+    let mut heap: FibHeap<i64> = xs.iter().copied().collect();
+    heap.pop();
+
+    let json = serde_json::to_string(&heap).unwrap();
+    let mut restored: FibHeap<i64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.len(), heap.len());
+
+    while let Some(x) = heap.pop() {
+        assert_eq!(restored.pop(), Some(x));
+    }
+    assert_eq!(restored.pop(), None);
+}
+
+#[test]
+fn tree_tokens() {
+    let tree: RbTree<u8> = [3, 1, 2].into_iter().collect();
+
+    assert_tokens(
+        &tree,
+        &[
+            Token::Seq { len: Some(3) },
+            Token::U8(1),
+            Token::U8(2),
+            Token::U8(3),
+            Token::SeqEnd,
+        ],
+    );
+}
+
+#[test]
+fn deserialize_unsorted_input() {
+    let tree: RbTree<u8> = serde_json::from_str("[5, 1, 5, 3, 1]").unwrap();
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(tree.inorder_traverse(), [&1, &3, &5]);
+
+    let map: RbMap<String, u8> = serde_json::from_str(r#"{"b": 2, "a": 1}"#).unwrap();
+    assert_eq!(map.validate(), Ok(()));
+    assert!(map
+        .iter()
+        .map(|(k, v)| (k.as_str(), *v))
+        .eq([("a", 1), ("b", 2)]));
+
+    let descending: RbTree<Reverse<u8>> = serde_json::from_str("[1, 3, 2]").unwrap();
+    assert!(descending.iter().map(|x| x.0).eq([3, 2, 1]));
+
+    assert!(serde_json::from_str::<RbTree<u8>>("{}").is_err());
+}